@@ -0,0 +1,130 @@
+//! Byte orders, for formats that store values in the byte order of the host
+//! that wrote them and mark it with a flag or a magic number.
+
+/// Generates the Int implementations of the integer types.
+macro_rules! impl_int {
+    ($($type:ty: $n:literal;)*) => {
+        $(
+            impl Int<$n> for $type {
+                fn from_be_bytes(x: [u8; $n]) -> Self { <$type>::from_be_bytes(x) }
+                fn from_le_bytes(x: [u8; $n]) -> Self { <$type>::from_le_bytes(x) }
+                fn to_be_bytes(self) -> [u8; $n] { <$type>::to_be_bytes(self) }
+                fn to_le_bytes(self) -> [u8; $n] { <$type>::to_le_bytes(self) }
+            }
+        )*
+    };
+}
+
+/// Integers of N bytes, which can be decoded and encoded in either byte order.
+pub trait Int<const N: usize>: Copy + Eq {
+    fn from_be_bytes(x: [u8; N]) -> Self;
+    fn from_le_bytes(x: [u8; N]) -> Self;
+    fn to_be_bytes(self) -> [u8; N];
+    fn to_le_bytes(self) -> [u8; N];
+}
+
+impl_int! {
+    u8: 1;
+    i8: 1;
+    u16: 2;
+    i16: 2;
+    u32: 4;
+    i32: 4;
+    u64: 8;
+    i64: 8;
+    u128: 16;
+    i128: 16;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    Big,
+    Little,
+}
+
+impl Endianness {
+    /// Byte order of the host we're running on.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") { Endianness::Big } else { Endianness::Little }
+    }
+
+    /// The byte order in which the bytes hold the magic number, if any.
+    pub fn detect<T: Int<N>, const N: usize>(x: [u8; N], magic: T) -> Option<Self> {
+        if T::from_be_bytes(x) == magic {
+            Some(Endianness::Big)
+        } else if T::from_le_bytes(x) == magic {
+            Some(Endianness::Little)
+        } else {
+            None
+        }
+    }
+
+    pub fn decode<T: Int<N>, const N: usize>(&self, x: [u8; N]) -> T {
+        match self {
+            Endianness::Big => T::from_be_bytes(x),
+            Endianness::Little => T::from_le_bytes(x),
+        }
+    }
+
+    pub fn encode<T: Int<N>, const N: usize>(&self, x: T) -> [u8; N] {
+        match self {
+            Endianness::Big => x.to_be_bytes(),
+            Endianness::Little => x.to_le_bytes(),
+        }
+    }
+
+    pub fn i16(&self, x: [u8; 2]) -> i16 {
+        self.decode(x)
+    }
+
+    pub fn u16(&self, x: [u8; 2]) -> u16 {
+        self.decode(x)
+    }
+
+    pub fn i32(&self, x: [u8; 4]) -> i32 {
+        self.decode(x)
+    }
+
+    pub fn u32(&self, x: [u8; 4]) -> u32 {
+        self.decode(x)
+    }
+
+    pub fn i64(&self, x: [u8; 8]) -> i64 {
+        self.decode(x)
+    }
+
+    pub fn u64(&self, x: [u8; 8]) -> u64 {
+        self.decode(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: u64 = 0x00bab10c;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Endianness::detect(MAGIC.to_be_bytes(), MAGIC), Some(Endianness::Big));
+        assert_eq!(Endianness::detect(MAGIC.to_le_bytes(), MAGIC), Some(Endianness::Little));
+    }
+
+    #[test]
+    fn detect_garbage() {
+        assert_eq!(Endianness::detect([0; 8], MAGIC), None);
+        assert_eq!(Endianness::detect([0xde, 0xad, 0xbe, 0xef, 0, 0, 0, 0], MAGIC), None);
+        assert_eq!(Endianness::detect(0x0cb1ba00u64.to_be_bytes(), MAGIC), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        for e in [Endianness::Big, Endianness::Little] {
+            assert_eq!(e.decode::<i16, 2>(e.encode(-2i16)), -2);
+            assert_eq!(e.u32(e.encode(0xdeadbeefu32)), 0xdeadbeef);
+            assert_eq!(e.u64(e.encode(MAGIC)), MAGIC);
+        }
+        assert_eq!(Endianness::Big.encode(1u32), [0, 0, 0, 1]);
+        assert_eq!(Endianness::Little.encode(1u32), [1, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,183 @@
+use std::fmt::{Display, Formatter};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Error raised while decoding, locating where it happened: the offset in the
+/// source, the type being decoded and the path leading to the value, e.g.
+/// `vdev_tree.children[1].guid`.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    offset: Option<u64>,
+    type_name: Option<&'static str>,
+    // Segments are added while the error propagates, so the innermost one
+    // comes first.
+    path: Vec<Segment>,
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Reading from the source failed, e.g. because it ended too early.
+    Io(std::io::Error),
+    /// The bytes read don't encode a valid value.
+    Invalid(Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Name(String),
+    Index(usize),
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Error { kind, offset: None, type_name: None, path: Vec::new() }
+    }
+
+    pub fn invalid(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::new(ErrorKind::Invalid(e.into()))
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+
+    pub fn type_name(&self) -> Option<&'static str> {
+        self.type_name
+    }
+
+    /// Path to the value, starting from the outermost segment.
+    pub fn path(&self) -> impl Iterator<Item = &Segment> {
+        self.path.iter().rev()
+    }
+
+    /// Sets the offset the error happened at, unless a more precise one has
+    /// already been set.
+    pub fn at(mut self, offset: u64) -> Self {
+        self.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Locates an error raised while decoding a buffer read at the given
+    /// offset of the source, so that it reports offsets in the source.
+    pub fn within(mut self, offset: u64) -> Self {
+        self.offset = Some(offset + self.offset.unwrap_or(0));
+        self
+    }
+
+    /// Sets the type being decoded, unless an inner one has already been set.
+    pub fn decoding(mut self, type_name: &'static str) -> Self {
+        self.type_name.get_or_insert(type_name);
+        self
+    }
+
+    /// Prepends a named field to the path.
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.path.push(Segment::Name(name.into()));
+        self
+    }
+
+    /// Prepends an array index to the path.
+    pub fn element(mut self, index: usize) -> Self {
+        self.path.push(Segment::Index(index));
+        self
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::Io(e) => write!(f, "{}", e)?,
+            ErrorKind::Invalid(e) => write!(f, "{}", e)?,
+        }
+        if let Some(type_name) = self.type_name {
+            write!(f, " while decoding {}", type_name)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {:#x}", offset)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in ")?;
+            for (i, segment) in self.path().enumerate() {
+                match segment {
+                    Segment::Name(name) if i == 0 => write!(f, "{}", name)?,
+                    Segment::Name(name) => write!(f, ".{}", name)?,
+                    Segment::Index(index) => write!(f, "[{}]", index)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Io(e) => Some(e),
+            ErrorKind::Invalid(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::new(ErrorKind::Io(e))
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e.kind {
+            ErrorKind::Io(e) => e.kind(),
+            ErrorKind::Invalid(_) => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+/// Adds context to the error of a Result, see the methods of Error.
+pub trait Context<T> {
+    fn at(self, offset: u64) -> Result<T>;
+    fn within(self, offset: u64) -> Result<T>;
+    fn decoding(self, type_name: &'static str) -> Result<T>;
+    fn field(self, name: &str) -> Result<T>;
+    fn element(self, index: usize) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> Context<T> for std::result::Result<T, E> {
+    fn at(self, offset: u64) -> Result<T> {
+        self.map_err(|e| e.into().at(offset))
+    }
+
+    fn within(self, offset: u64) -> Result<T> {
+        self.map_err(|e| e.into().within(offset))
+    }
+
+    fn decoding(self, type_name: &'static str) -> Result<T> {
+        self.map_err(|e| e.into().decoding(type_name))
+    }
+
+    fn field(self, name: &str) -> Result<T> {
+        self.map_err(|e| e.into().field(name))
+    }
+
+    fn element(self, index: usize) -> Result<T> {
+        self.map_err(|e| e.into().element(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn within() {
+        assert_eq!(Error::invalid("x").at(4).within(0x4000).offset(), Some(0x4004));
+        assert_eq!(Error::invalid("x").within(0x4000).offset(), Some(0x4000));
+        assert_eq!(Error::invalid("x").within(0x4000).at(8).offset(), Some(0x4000));
+    }
+}
@@ -1,3 +1,11 @@
+mod endian;
+mod error;
 mod reader;
+mod slice;
+mod writer;
 
-pub use crate::reader::Reader;
+pub use crate::endian::{Endianness, Int};
+pub use crate::error::{Context, Error, ErrorKind, Result, Segment};
+pub use crate::reader::{DEFAULT_MAX_ALLOC, Reader, Source};
+pub use crate::slice::SliceReader;
+pub use crate::writer::Writer;
@@ -1,44 +1,111 @@
-use std::fs::File;
-use std::io::{BufReader, Error, Read, Result, Seek, SeekFrom};
+use std::fmt::{Debug, Formatter};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 use std::result::Result as StdResult;
 
-// TODO: Reader should be parametric around the Read + Seek trait.
-//       However, this would make the code way more complex and verbose,
-//       as it would require clients to specify such trait all over the place.
-//       Right now wr're using it for reading Files only, and such additional
-//       complexity can't be justified (yet).
-// TODO: Is it ok to capture a reference to File? Should Reader own it? Who knows.
-#[derive(Debug)]
-pub struct Reader<'a>(BufReader<&'a File>);
+use crate::endian::{Endianness, Int};
+use crate::error::{Context, Error, Result};
+
+/// Anything a Reader can read from: files (or references to them), in-memory
+/// buffers wrapped in a Cursor and so on.
+pub trait Source: Read + Seek {}
+
+impl<T: Read + Seek> Source for T {}
+
+/// Default limit on the size of a single allocation made while decoding, see
+/// Reader::with_max_alloc().
+pub const DEFAULT_MAX_ALLOC: usize = 16 * 1024 * 1024;
+
+// Reader is not parametric around its source: that would require clients to
+// specify the Read + Seek bounds all over the place. The source is boxed
+// instead, at the cost of a dynamic dispatch for every buffer refill.
+pub struct Reader<'a> {
+    source: BufReader<Box<dyn Source + 'a>>,
+    // Tracked here to locate errors, as asking the source for it may take a
+    // syscall. None until first needed and after a failed read.
+    position: Option<u64>,
+    max_alloc: usize,
+}
 
 impl<'a> Reader<'a> {
-    pub fn new(file: &'a File) -> Self {
-        Reader(BufReader::new(file))
+    pub fn new(source: impl Source + 'a) -> Self {
+        Reader {
+            source: BufReader::new(Box::new(source)),
+            position: None,
+            max_alloc: DEFAULT_MAX_ALLOC,
+        }
+    }
+
+    /// Limits the size of the buffers allocated by read_to() and
+    /// try_read_to(), as well as the ones checked with check_alloc(). Lengths
+    /// read from untrusted sources can't then make us allocate gigabytes.
+    pub fn with_max_alloc(mut self, max_alloc: usize) -> Self {
+        self.max_alloc = max_alloc;
+        self
+    }
+
+    pub fn max_alloc(&self) -> usize {
+        self.max_alloc
+    }
+
+    /// Fails if allocating len bytes would exceed the limit set with
+    /// with_max_alloc().
+    pub fn check_alloc(&mut self, len: usize) -> Result<()> {
+        if len > self.max_alloc {
+            let pos = self.position()?;
+            return Err(Error::invalid(format!(
+                "can't allocate {} bytes, the limit is {}", len, self.max_alloc
+            )).at(pos))
+        }
+        Ok(())
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        Reader::new(Cursor::new(bytes))
+    }
+
+    pub fn position(&mut self) -> Result<u64> {
+        match self.position {
+            Some(pos) => Ok(pos),
+            None => {
+                let pos = self.source.stream_position()?;
+                self.position = Some(pos);
+                Ok(pos)
+            }
+        }
     }
 
     pub fn read(&mut self, buf: &mut [u8]) -> Result<()> {
-        self.0.read_exact(buf)
+        let pos = self.position()?;
+        self.position = None;
+        self.source.read_exact(buf).at(pos)?;
+        self.position = Some(pos + buf.len() as u64);
+        Ok(())
     }
 
     pub fn seek(&mut self, pos: SeekFrom) -> Result<u64>{
-        self.0.seek(pos)
+        self.position = None;
+        let pos = self.source.seek(pos)?;
+        self.position = Some(pos);
+        Ok(pos)
     }
 
     pub fn skip(&mut self, offset: u32) -> Result<u64> {
-        self.0.seek_relative(offset.into())?;
-        self.0.stream_position()
+        let pos = self.position()?;
+        self.position = None;
+        self.source.seek_relative(offset.into()).at(pos)?;
+        self.position = Some(pos + u64::from(offset));
+        Ok(pos + u64::from(offset))
     }
 
     pub fn align(&mut self, to: u32) -> Result<u64> {
-        let to: u64 = to.into();
-        let pos = self.0.stream_position()?;
-        let dest = (pos + (to - 1)) & !(to - 1);
-        let amount: u32 = (dest - pos).try_into().expect(&format!(
-            "can't fit result of {} - {} \
-            (alignment destination - current position) into a u32 \
-            while trying to align to {} bytes boundary",
-            dest, pos, to
-        ));
+        if !to.is_power_of_two() {
+            return Err(Error::invalid(format!(
+                "can't align to {} bytes, not a power of two", to
+            )))
+        }
+        let pos = self.position()?;
+        // Less than to, so it always fits.
+        let amount = ((to - 1) as u64 & pos.wrapping_neg()) as u32;
         self.skip(amount)
     }
 
@@ -51,6 +118,14 @@ impl<'a> Reader<'a> {
         Ok(f(&buf))
     }
 
+    /// Reads an integer in the given byte order.
+    pub fn read_int<T: Int<N>, const N: usize>(
+        &mut self,
+        e: Endianness
+    ) -> Result<T> {
+        self.read_as(|x| e.decode(*x))
+    }
+
     pub fn try_read_as<T, E, const N: usize>(
         &mut self,
         f: impl FnOnce(&[u8; N]) -> StdResult<T, E>
@@ -58,9 +133,10 @@ impl<'a> Reader<'a> {
     where
         Error: From<E>
     {
+        let pos = self.position()?;
         let mut buf = [0u8; N];
         self.read(&mut buf)?;
-        Ok(f(&buf)?)
+        f(&buf).map_err(Error::from).at(pos)
     }
 
     pub fn read_to<T>(
@@ -68,6 +144,7 @@ impl<'a> Reader<'a> {
         len: usize,
         f: impl FnOnce(Vec<u8>) -> T
     ) -> Result<T> {
+        self.check_alloc(len)?;
         let mut buf = vec![0; len];
         self.read(&mut buf)?;
         Ok(f(buf))
@@ -81,8 +158,125 @@ impl<'a> Reader<'a> {
     where
         Error: From<E>
     {
+        self.check_alloc(len)?;
+        let pos = self.position()?;
         let mut buf = vec![0; len];
         self.read(&mut buf)?;
-        Ok(f(buf)?)
+        f(buf).map_err(Error::from).at(pos)
+    }
+}
+
+impl Debug for Reader<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reader")
+            .field("position", &self.position)
+            .field("max_alloc", &self.max_alloc)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+
+    #[test]
+    fn from_bytes() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        let mut buf = [0; 3];
+        r.read(&mut buf)?;
+        assert_eq!(buf, [1, 2, 3]);
+        assert_eq!(r.read_as(|x: &[u8; 2]| u16::from_be_bytes(*x))?, 0x0405);
+        assert_eq!(r.read_to(2, |x| x)?, [6, 7]);
+        Ok(())
+    }
+
+    #[test]
+    fn cursor() -> Result<()> {
+        let mut r = Reader::new(Cursor::new(BYTES.to_vec()));
+        assert_eq!(r.read_as(|x: &[u8; 4]| u32::from_le_bytes(*x))?, 0x04030201);
+        assert_eq!(r.read_as(|x: &[u8; 4]| u32::from_be_bytes(*x))?, 0x05060708);
+        Ok(())
+    }
+
+    #[test]
+    fn positions() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        assert_eq!(r.skip(3)?, 3);
+        assert_eq!(r.read_as(|x: &[u8; 1]| x[0])?, 4);
+        assert_eq!(r.seek(SeekFrom::Current(2))?, 6);
+        assert_eq!(r.seek(SeekFrom::End(-1))?, 11);
+        assert_eq!(r.seek(SeekFrom::Start(1))?, 1);
+        assert_eq!(r.read_as(|x: &[u8; 1]| x[0])?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn align() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        assert_eq!(r.align(4)?, 0);
+        r.skip(1)?;
+        assert_eq!(r.align(4)?, 4);
+        assert_eq!(r.align(4)?, 4);
+        assert_eq!(r.align(8)?, 8);
+        assert_eq!(r.read_as(|x: &[u8; 1]| x[0])?, 9);
+        Ok(())
+    }
+
+    #[test]
+    fn position() -> Result<()> {
+        let mut r = Reader::new(Cursor::new(BYTES.to_vec()));
+        assert_eq!(r.position()?, 0);
+        r.read_to(3, |x| x)?;
+        assert_eq!(r.position()?, 3);
+        r.align(4)?;
+        assert_eq!(r.position()?, 4);
+        r.seek(SeekFrom::End(-2))?;
+        assert_eq!(r.position()?, 10);
+        Ok(())
+    }
+
+    #[test]
+    fn read_int() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        assert_eq!(r.read_int::<u16, 2>(Endianness::Big)?, 0x0102);
+        assert_eq!(r.read_int::<u16, 2>(Endianness::Little)?, 0x0403);
+        assert_eq!(r.read_int::<i32, 4>(Endianness::Little)?, 0x08070605);
+        assert_eq!(r.read_int::<u32, 4>(Endianness::Big)?, 0x090a0b0c);
+        assert!(r.read_int::<u8, 1>(Endianness::Big).is_err());
+
+        let mut r = Reader::from_bytes(&BYTES);
+        assert_eq!(r.read_int::<u64, 8>(Endianness::Big)?, 0x0102030405060708);
+        r.seek(SeekFrom::Start(0))?;
+        assert_eq!(r.read_int::<u64, 8>(Endianness::Little)?, 0x0807060504030201);
+        Ok(())
+    }
+
+    #[test]
+    fn eof() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        r.skip(8)?;
+        let err = r.read_to(5, |x| x).unwrap_err();
+        assert_eq!(err.offset(), Some(8));
+        // After a failed read, the position is taken from the source again.
+        assert_eq!(r.position()?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn max_alloc() -> Result<()> {
+        let mut r = Reader::from_bytes(&BYTES);
+        assert_eq!(r.max_alloc(), DEFAULT_MAX_ALLOC);
+        r.check_alloc(DEFAULT_MAX_ALLOC)?;
+        assert!(r.check_alloc(DEFAULT_MAX_ALLOC + 1).is_err());
+        // Lengths are checked before allocating, let alone reading.
+        assert!(r.read_to(usize::MAX, |x| x).is_err());
+
+        let mut r = Reader::from_bytes(&BYTES).with_max_alloc(4);
+        assert!(r.try_read_to(5, Ok::<_, Error>).is_err());
+        assert_eq!(r.position()?, 0);
+        assert_eq!(r.read_to(4, |x| x)?, [1, 2, 3, 4]);
+        Ok(())
     }
 }
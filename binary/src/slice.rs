@@ -0,0 +1,263 @@
+use std::io::ErrorKind;
+use std::result::Result as StdResult;
+
+use crate::endian::{Endianness, Int};
+use crate::error::{Context, Error, Result};
+
+/// Generates methods reading integers of the given type and byte order.
+macro_rules! read_int {
+    ($($name:ident: $type:ty, $from_bytes:ident;)*) => {
+        $(
+            pub fn $name(&mut self) -> Result<$type> {
+                self.read_as(|x| <$type>::$from_bytes(*x))
+            }
+        )*
+    };
+}
+
+/// Decodes values out of an in-memory buffer, such as a block read from a
+/// vdev, without copying it. Its methods mirror the ones of Reader, but
+/// variable length reads return sub-slices of the buffer.
+#[derive(Clone, Debug)]
+pub struct SliceReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    // Offset of the buffer in its source, used to locate errors.
+    offset: u64,
+}
+
+impl<'a> SliceReader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        SliceReader::with_offset(buf, 0)
+    }
+
+    /// Creates a reader over a buffer read at the given offset of a source,
+    /// such as a vdev. Errors will be located relative to the source.
+    pub fn with_offset(buf: &'a [u8], offset: u64) -> Self {
+        SliceReader { buf, pos: 0, offset }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn seek(&mut self, pos: usize) -> Result<usize> {
+        if pos > self.buf.len() {
+            return Err(self.eof(pos - self.pos))
+        }
+        self.pos = pos;
+        Ok(self.pos)
+    }
+
+    pub fn skip(&mut self, len: usize) -> Result<usize> {
+        self.read(len)?;
+        Ok(self.pos)
+    }
+
+    pub fn align(&mut self, to: usize) -> Result<usize> {
+        if !to.is_power_of_two() {
+            return Err(Error::invalid(format!(
+                "can't align to {} bytes, not a power of two", to
+            )))
+        }
+        self.skip((to - 1) & self.pos.wrapping_neg())
+    }
+
+    pub fn read(&mut self, len: usize) -> Result<&'a [u8]> {
+        let x = self.pos.checked_add(len)
+            .and_then(|end| self.buf.get(self.pos..end))
+            .ok_or_else(|| self.eof(len))?;
+        self.pos += len;
+        Ok(x)
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<&'a [u8; N]> {
+        // The conversion can't fail, as exactly N bytes have been read.
+        Ok(self.read(N)?.try_into().unwrap())
+    }
+
+    pub fn read_as<T, const N: usize>(
+        &mut self,
+        f: impl FnOnce(&[u8; N]) -> T
+    ) -> Result<T> {
+        Ok(f(self.read_array()?))
+    }
+
+    /// Reads an integer in the given byte order.
+    pub fn read_int<T: Int<N>, const N: usize>(
+        &mut self,
+        e: Endianness
+    ) -> Result<T> {
+        self.read_as(|x| e.decode(*x))
+    }
+
+    pub fn try_read_as<T, E, const N: usize>(
+        &mut self,
+        f: impl FnOnce(&[u8; N]) -> StdResult<T, E>
+    ) -> Result<T>
+    where
+        Error: From<E>
+    {
+        let pos = self.source_position();
+        f(self.read_array()?).map_err(Error::from).at(pos)
+    }
+
+    pub fn read_to<T>(
+        &mut self,
+        len: usize,
+        f: impl FnOnce(&'a [u8]) -> T
+    ) -> Result<T> {
+        Ok(f(self.read(len)?))
+    }
+
+    pub fn try_read_to<T, E>(
+        &mut self,
+        len: usize,
+        f: impl FnOnce(&'a [u8]) -> StdResult<T, E>
+    ) -> Result<T>
+    where
+        Error: From<E>
+    {
+        let pos = self.source_position();
+        f(self.read(len)?).map_err(Error::from).at(pos)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        self.read_as(|x: &[u8; 1]| x[0])
+    }
+
+    pub fn read_i8(&mut self) -> Result<i8> {
+        self.read_as(|x| i8::from_ne_bytes(*x))
+    }
+
+    read_int! {
+        read_u16_le: u16, from_le_bytes;
+        read_u16_be: u16, from_be_bytes;
+        read_i16_le: i16, from_le_bytes;
+        read_i16_be: i16, from_be_bytes;
+        read_u32_le: u32, from_le_bytes;
+        read_u32_be: u32, from_be_bytes;
+        read_i32_le: i32, from_le_bytes;
+        read_i32_be: i32, from_be_bytes;
+        read_u64_le: u64, from_le_bytes;
+        read_u64_be: u64, from_be_bytes;
+        read_i64_le: i64, from_le_bytes;
+        read_i64_be: i64, from_be_bytes;
+    }
+
+    fn source_position(&self) -> u64 {
+        self.offset + self.pos as u64
+    }
+
+    fn eof(&self, len: usize) -> Error {
+        Error::from(std::io::Error::new(ErrorKind::UnexpectedEof, format!(
+            "can't read {} bytes past the end of a {} bytes buffer",
+            len, self.buf.len()
+        ))).at(self.source_position())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BYTES: [u8; 12] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 0xff];
+
+    #[test]
+    fn read() -> Result<()> {
+        let mut r = SliceReader::new(&BYTES);
+        assert_eq!(r.read(3)?, [1, 2, 3]);
+        assert_eq!(r.read_array()?, &[4, 5]);
+        assert_eq!(r.read_to(2, |x| x.len())?, 2);
+        assert_eq!(r.position(), 7);
+        assert_eq!(r.remaining(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut r = SliceReader::new(&BYTES);
+        let err = r.read(13).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            crate::ErrorKind::Io(e) if e.kind() == ErrorKind::UnexpectedEof
+        ));
+        assert_eq!(
+            err.to_string(),
+            "can't read 13 bytes past the end of a 12 bytes buffer at offset 0x0"
+        );
+        assert_eq!(r.position(), 0);
+        assert!(r.read(usize::MAX).is_err());
+        assert!(r.seek(13).is_err());
+        assert!(r.skip(13).is_err());
+    }
+
+    #[test]
+    fn eof() -> Result<()> {
+        let mut r = SliceReader::new(&BYTES);
+        r.seek(BYTES.len())?;
+        assert_eq!(r.remaining(), 0);
+        assert_eq!(r.read(0)?, []);
+        assert!(r.read_u8().is_err());
+        r.seek(10)?;
+        assert!(r.read_u32_le().is_err());
+        assert_eq!(r.position(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn source_offset() -> Result<()> {
+        let mut r = SliceReader::with_offset(&BYTES, 0x1000);
+        r.skip(10)?;
+        assert_eq!(r.read_u32_le().unwrap_err().offset(), Some(0x100a));
+        let err = r.try_read_as(|_: &[u8; 1]| Err::<u8, _>(Error::invalid("bad"))).unwrap_err();
+        assert_eq!(err.offset(), Some(0x100a));
+        Ok(())
+    }
+
+    #[test]
+    fn seek_and_skip() -> Result<()> {
+        let mut r = SliceReader::new(&BYTES);
+        assert_eq!(r.skip(5)?, 5);
+        assert_eq!(r.seek(2)?, 2);
+        assert_eq!(r.read_u8()?, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn align() -> Result<()> {
+        let mut r = SliceReader::new(&BYTES);
+        assert_eq!(r.align(4)?, 0);
+        r.skip(1)?;
+        assert_eq!(r.align(4)?, 4);
+        assert_eq!(r.align(8)?, 8);
+        r.skip(1)?;
+        assert!(r.align(16).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ints() -> Result<()> {
+        let mut r = SliceReader::new(&BYTES);
+        assert_eq!(r.read_u16_le()?, 0x0201);
+        assert_eq!(r.read_u16_be()?, 0x0304);
+        assert_eq!(r.read_u32_le()?, 0x08070605);
+        r.seek(0)?;
+        assert_eq!(r.read_u64_be()?, 0x0102030405060708);
+        assert_eq!(r.read_i8()?, 9);
+        r.seek(10)?;
+        assert_eq!(r.read_i16_le()?, -245);
+        r.seek(8)?;
+        assert_eq!(r.read_i32_be()?, 0x090a0bff);
+        r.seek(4)?;
+        assert_eq!(r.read_i64_le()?, -0xf4f5f6f7f8f9fb);
+        r.seek(0)?;
+        assert_eq!(r.read_int::<u32, 4>(Endianness::Big)?, 0x01020304);
+        assert_eq!(r.read_int::<u32, 4>(Endianness::Little)?, 0x08070605);
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::{Debug, Formatter};
+use std::io::{BufWriter, Write};
+
+use crate::endian::{Endianness, Int};
+use crate::error::{Context, Error, Result};
+
+// Like Reader, Writer boxes its sink rather than being parametric around it.
+// Writes are buffered: call flush() to catch the errors dropping would ignore.
+pub struct Writer<'a> {
+    sink: BufWriter<Box<dyn Write + 'a>>,
+    // Bytes written so far, used to align and to locate errors.
+    position: u64,
+}
+
+impl<'a> Writer<'a> {
+    pub fn new(sink: impl Write + 'a) -> Self {
+        Writer { sink: BufWriter::new(Box::new(sink)), position: 0 }
+    }
+
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<()> {
+        self.sink.write_all(buf).at(self.position)?;
+        self.position += buf.len() as u64;
+        Ok(())
+    }
+
+    /// Writes len zero bytes.
+    pub fn pad(&mut self, len: u32) -> Result<u64> {
+        const ZEROS: [u8; 64] = [0; 64];
+        let mut left = len as usize;
+        while left > 0 {
+            let n = left.min(ZEROS.len());
+            self.write(&ZEROS[..n])?;
+            left -= n;
+        }
+        Ok(self.position)
+    }
+
+    /// Pads with zero bytes up to a multiple of to bytes written.
+    pub fn align(&mut self, to: u32) -> Result<u64> {
+        if !to.is_power_of_two() {
+            return Err(Error::invalid(format!(
+                "can't align to {} bytes, not a power of two", to
+            )))
+        }
+        // Less than to, so it always fits.
+        let amount = ((to - 1) as u64 & self.position.wrapping_neg()) as u32;
+        self.pad(amount)
+    }
+
+    pub fn write_as<T, const N: usize>(
+        &mut self,
+        x: &T,
+        f: impl FnOnce(&T) -> [u8; N]
+    ) -> Result<()> {
+        self.write(&f(x))
+    }
+
+    /// Writes an integer in the given byte order.
+    pub fn write_int<T: Int<N>, const N: usize>(
+        &mut self,
+        e: Endianness,
+        x: T
+    ) -> Result<()> {
+        self.write(&e.encode(x))
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.sink.flush().at(self.position)
+    }
+
+    /// Collects into a vector whatever f writes.
+    pub fn to_vec(f: impl FnOnce(&mut Writer) -> Result<()>) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut w = Writer::new(&mut buf);
+        f(&mut w)?;
+        w.flush()?;
+        drop(w);
+        Ok(buf)
+    }
+}
+
+impl Debug for Writer<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Writer")
+            .field("position", &self.position)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write() -> Result<()> {
+        let bytes = Writer::to_vec(|w| {
+            w.write(&[1, 2, 3])?;
+            assert_eq!(w.position(), 3);
+            w.write_as(&0x0405u16, |x| x.to_be_bytes())?;
+            assert_eq!(w.pad(2)?, 7);
+            Ok(())
+        })?;
+        assert_eq!(bytes, [1, 2, 3, 4, 5, 0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn write_int() -> Result<()> {
+        let bytes = Writer::to_vec(|w| {
+            w.write_int(Endianness::Big, 0x0102u16)?;
+            w.write_int(Endianness::Little, 0x0102u16)?;
+            w.write_int(Endianness::Little, -2i32)
+        })?;
+        assert_eq!(bytes, [1, 2, 2, 1, 0xfe, 0xff, 0xff, 0xff]);
+        Ok(())
+    }
+
+    #[test]
+    fn align() -> Result<()> {
+        let bytes = Writer::to_vec(|w| {
+            assert_eq!(w.align(4)?, 0);
+            w.write(&[1])?;
+            assert_eq!(w.align(4)?, 4);
+            assert_eq!(w.align(4)?, 4);
+            assert_eq!(w.align(8)?, 8);
+            assert!(w.align(3).is_err());
+            Ok(())
+        })?;
+        assert_eq!(bytes, [1, 0, 0, 0, 0, 0, 0, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn pad() -> Result<()> {
+        // More than the zeros written at once.
+        let bytes = Writer::to_vec(|w| w.pad(100).map(|_| ()))?;
+        assert_eq!(bytes, [0; 100]);
+        Ok(())
+    }
+
+    #[test]
+    fn full_sink() {
+        let mut buf = [0u8; 4];
+        let mut w = Writer::new(&mut buf[..]);
+        w.write(&[1, 2, 3]).unwrap();
+        w.write(&[4, 5, 6]).unwrap();
+        // Buffered writes only fail once flushed.
+        let err = w.flush().unwrap_err();
+        assert_eq!(err.offset(), Some(6));
+    }
+}
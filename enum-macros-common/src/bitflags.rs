@@ -0,0 +1,286 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, ItemEnum, TypePath};
+
+use crate::int_enum::common::get_variants;
+
+pub fn bitflags(e: &ItemEnum, t: &TypePath) -> TokenStream {
+    match r#impl(e, t) {
+        Ok(ts) => ts,
+        Err(err) => err.to_compile_error()
+    }
+}
+
+fn r#impl(e: &ItemEnum, t: &TypePath) -> Result<TokenStream, Error> {
+    let vs = get_variants(e)?;
+    let flags = common::get_flags(e, &vs);
+
+    let set_impl = impl_set::r#impl(e, &flags, t);
+    let debug_impl = impl_debug::r#impl(&e.ident);
+    let ops_impl = impl_ops::r#impl(&e.ident);
+    let conversions_impl = impl_conversions::r#impl(&e.ident, t);
+    let checks = common::single_bit_checks(&e.ident, &flags, t);
+    Ok(quote! { #set_impl #debug_impl #ops_impl #conversions_impl #checks })
+}
+
+pub mod common {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{Attribute, Expr, Ident, ItemEnum, LitStr, TypePath};
+    use crate::int_enum::common::{Variant, Variants};
+
+    /// A variant of the enum, which becomes an associated constant of the
+    /// flag set.
+    pub struct Flag<'a> {
+        pub attrs: &'a [Attribute],
+        pub ident: Ident,
+        pub name: LitStr,
+        pub expr: &'a Expr
+    }
+
+    pub fn get_flags<'a>(e: &'a ItemEnum, vs: &Variants<'a>) -> Vec<Flag<'a>> {
+        e.variants.iter().zip(vs).map(|(v, Variant { ident, expr })| {
+            let name = screaming_snake_case(&ident.to_string());
+            Flag {
+                attrs: &v.attrs,
+                ident: Ident::new(&name, ident.span()),
+                name: LitStr::new(&name, ident.span()),
+                expr
+            }
+        }).collect()
+    }
+
+    /// `UniqueNameType` becomes `UNIQUE_NAME_TYPE` and `MMPValid` becomes
+    /// `MMP_VALID`.
+    fn screaming_snake_case(s: &str) -> String {
+        let cs: Vec<char> = s.chars().collect();
+        let mut out = String::with_capacity(s.len() + 4);
+        for (i, &c) in cs.iter().enumerate() {
+            if i > 0 && c.is_uppercase() {
+                let prev = cs[i - 1];
+                let next = cs.get(i + 1).copied();
+                if prev.is_lowercase() || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next.is_some_and(char::is_lowercase)) {
+                    out.push('_');
+                }
+            }
+            out.extend(c.to_uppercase());
+        }
+        out
+    }
+
+    /// Compile-time assertions that each discriminant is a single bit.
+    pub fn single_bit_checks(
+        set_ident: &Ident,
+        flags: &[Flag],
+        int_type: &TypePath
+    ) -> TokenStream {
+        let checks = flags.iter().map(|Flag { ident, expr, .. }| quote! {
+            const _: () = ::core::assert!(
+                ((#expr) as #int_type).count_ones() == 1,
+                ::core::concat!(
+                    ::core::stringify!(#set_ident), "::",
+                    ::core::stringify!(#ident), " must be a single bit"
+                )
+            );
+        });
+        quote! { #(#checks)* }
+    }
+}
+
+pub mod impl_set {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{ItemEnum, TypePath};
+    use crate::bitflags::common::Flag;
+
+    pub fn r#impl(e: &ItemEnum, flags: &[Flag], int_type: &TypePath) -> TokenStream {
+        let ItemEnum { attrs, vis, ident, .. } = e;
+        let consts = flags.iter().map(|Flag { attrs, ident, expr, .. }| quote! {
+            #(#attrs)*
+            pub const #ident: Self = Self(#expr);
+        });
+        let named = flags.iter().map(
+            |Flag { ident, name, .. }| quote! { (#name, Self::#ident) }
+        );
+        let all = flags.iter().map(|Flag { ident, .. }| quote! { Self::#ident.0 });
+        quote! {
+            #(#attrs)*
+            #[derive(
+                ::core::clone::Clone, ::core::marker::Copy,
+                ::core::default::Default, ::core::hash::Hash,
+                ::core::cmp::PartialEq, ::core::cmp::Eq
+            )]
+            #vis struct #ident(#int_type);
+
+            impl #ident {
+                #(#consts)*
+
+                /// The known flags and their names, in declaration order.
+                pub const NAMED: &'static [(&'static ::core::primitive::str, Self)] =
+                    &[#(#named),*];
+
+                pub const fn empty() -> Self {
+                    Self(0)
+                }
+
+                /// All the known flags.
+                pub const fn all() -> Self {
+                    Self(0 #(| #all)*)
+                }
+
+                /// Keeps the bits that don't match any known flag.
+                pub const fn from_bits_retain(bits: #int_type) -> Self {
+                    Self(bits)
+                }
+
+                pub const fn bits(&self) -> #int_type {
+                    self.0
+                }
+
+                /// The bits that don't match any known flag.
+                pub const fn unknown(&self) -> Self {
+                    Self(self.0 & !Self::all().0)
+                }
+
+                pub const fn is_empty(&self) -> bool {
+                    self.0 == 0
+                }
+
+                pub const fn contains(&self, other: Self) -> bool {
+                    self.0 & other.0 == other.0
+                }
+
+                pub const fn intersects(&self, other: Self) -> bool {
+                    self.0 & other.0 != 0
+                }
+
+                pub fn insert(&mut self, other: Self) {
+                    self.0 |= other.0;
+                }
+
+                pub fn remove(&mut self, other: Self) {
+                    self.0 &= !other.0;
+                }
+
+                /// The known flags that are set, in declaration order.
+                pub fn iter(&self) -> impl ::core::iter::Iterator<Item = Self> {
+                    let set = *self;
+                    Self::NAMED.iter()
+                        .map(|(_, flag)| *flag)
+                        .filter(move |flag| set.contains(*flag))
+                }
+            }
+        }
+    }
+}
+
+pub mod impl_debug {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::Ident;
+
+    /// Prints the names of the set flags followed by the unknown bits, e.g.
+    /// `Flags(UNIQUE_NAME | 0x8)`, or `Flags(0x0)` when none is set.
+    pub fn r#impl(set_ident: &Ident) -> TokenStream {
+        quote! {
+            impl ::core::fmt::Debug for #set_ident {
+                fn fmt(
+                    &self,
+                    f: &mut ::core::fmt::Formatter<'_>
+                ) -> ::core::fmt::Result {
+                    f.write_str(::core::concat!(::core::stringify!(#set_ident), "("))?;
+                    let mut first = true;
+                    for (name, flag) in Self::NAMED {
+                        if self.contains(*flag) {
+                            if !first {
+                                f.write_str(" | ")?;
+                            }
+                            f.write_str(name)?;
+                            first = false;
+                        }
+                    }
+                    let unknown = self.unknown();
+                    if first || !unknown.is_empty() {
+                        if !first {
+                            f.write_str(" | ")?;
+                        }
+                        ::core::write!(f, "{:#x}", unknown.0)?;
+                    }
+                    f.write_str(")")
+                }
+            }
+        }
+    }
+}
+
+pub mod impl_ops {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::Ident;
+
+    pub fn r#impl(set_ident: &Ident) -> TokenStream {
+        quote! {
+            impl ::core::ops::BitOr for #set_ident {
+                type Output = Self;
+
+                fn bitor(self, rhs: Self) -> Self {
+                    Self(self.0 | rhs.0)
+                }
+            }
+
+            impl ::core::ops::BitOrAssign for #set_ident {
+                fn bitor_assign(&mut self, rhs: Self) {
+                    self.0 |= rhs.0;
+                }
+            }
+
+            impl ::core::ops::BitAnd for #set_ident {
+                type Output = Self;
+
+                fn bitand(self, rhs: Self) -> Self {
+                    Self(self.0 & rhs.0)
+                }
+            }
+
+            impl ::core::iter::FromIterator<#set_ident> for #set_ident {
+                fn from_iter<I: ::core::iter::IntoIterator<Item = Self>>(
+                    iter: I
+                ) -> Self {
+                    iter.into_iter().fold(Self(0), |a, b| a | b)
+                }
+            }
+        }
+    }
+}
+
+pub mod impl_conversions {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{Ident, TypePath};
+
+    /// The integer converts to the flag set only if all its bits are known,
+    /// as int_enum does; from_bits_retain keeps the unknown ones.
+    pub fn r#impl(set_ident: &Ident, int_type: &TypePath) -> TokenStream {
+        quote! {
+            impl ::core::convert::From<#set_ident> for #int_type {
+                fn from(x: #set_ident) -> Self {
+                    x.0
+                }
+            }
+
+            impl ::core::convert::TryFrom<#int_type> for #set_ident {
+                type Error = #int_type;
+
+                fn try_from(
+                    x: #int_type
+                ) -> ::core::result::Result<Self, #int_type> {
+                    match #set_ident::from_bits_retain(x) {
+                        s if s.unknown().is_empty() => ::core::result::Result::Ok(s),
+                        _ => ::core::result::Result::Err(x)
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,12 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{ItemEnum, TypePath};
-
-pub fn int_enum(e: &ItemEnum, t: &TypePath) -> TokenStream {
-    match common::get_variants(e) {
-        Ok(vs) => {
-            let enum_impl = impl_from_enum::r#impl(&e.ident, &vs, &t);
-            let type_impl = impl_try_from_type::r#impl(&e.ident, &vs, &t);
-            quote! { #e #enum_impl #type_impl }
-        },
+use syn::{Error, ItemEnum};
+
+use crate::int_enum::options::Options;
+
+pub fn int_enum(e: &ItemEnum, o: &Options) -> TokenStream {
+    match r#impl(e, o) {
+        Ok(ts) => ts,
         Err(err) => {
             let err = err.to_compile_error();
             quote! { #e #err }
@@ -16,8 +14,84 @@ pub fn int_enum(e: &ItemEnum, t: &TypePath) -> TokenStream {
     }
 }
 
+fn r#impl(e: &ItemEnum, o: &Options) -> Result<TokenStream, Error> {
+    let t = &o.int_type;
+    let (known, unknown) = match o.unknown {
+        true => common::split_unknown(e, t)?,
+        false => (e.clone(), None),
+    };
+    let vs = common::get_variants(&known)?;
+    let names = common::get_names(&known)?;
+
+    let enum_impl = impl_from_enum::r#impl(&e.ident, &vs, t, unknown.as_ref());
+    let type_impl = match &unknown {
+        Some(u) => impl_from_type::r#impl(&e.ident, &vs, t, u),
+        None => impl_try_from_type::r#impl(&e.ident, &vs, t),
+    };
+    let display_impl = match o.display {
+        true => impl_display::r#impl(&e.ident, &vs, &names, unknown.as_ref()),
+        false => quote! {},
+    };
+    let from_str_impl = match o.from_str {
+        true => impl_from_str::r#impl(&e.ident, &vs, &names, t, unknown.as_ref()),
+        false => quote! {},
+    };
+    let e = common::strip(e, unknown.is_some());
+    Ok(quote! { #e #enum_impl #type_impl #display_impl #from_str_impl })
+}
+
+pub mod options {
+    use syn::parse::{Parse, ParseStream};
+    use syn::punctuated::Punctuated;
+    use syn::{Error, Ident, Token, TypePath};
+
+    /// Arguments of the attribute: the integer type, optionally followed by
+    /// the names of the features to enable.
+    ///
+    ///  - unknown: the values that don't match any other variant are held by
+    ///    the `Unknown(int)` variant, making conversions infallible. The enum
+    ///    must declare it, without a discriminant.
+    ///  - display: implements Display, printing the names of the variants.
+    ///  - from_str: implements FromStr, parsing the names of the variants.
+    ///
+    /// Names default to the lowercase identifiers of the variants and can be
+    /// set with `#[int_enum(name = "...")]`.
+    pub struct Options {
+        pub int_type: TypePath,
+        pub unknown: bool,
+        pub display: bool,
+        pub from_str: bool,
+    }
+
+    impl Parse for Options {
+        fn parse(input: ParseStream) -> syn::Result<Self> {
+            let int_type = input.parse()?;
+            let mut options = Options {
+                int_type, unknown: false, display: false, from_str: false
+            };
+            if input.is_empty() {
+                return Ok(options)
+            }
+            input.parse::<Token![,]>()?;
+            for flag in Punctuated::<Ident, Token![,]>::parse_terminated(input)? {
+                match flag.to_string().as_str() {
+                    "unknown" => options.unknown = true,
+                    "display" => options.display = true,
+                    "from_str" => options.from_str = true,
+                    _ => return Err(Error::new(flag.span(), "unsupported int_enum option"))
+                }
+            }
+            Ok(options)
+        }
+    }
+}
+
 pub mod common {
-    use syn::{Error, Expr, Ident, ItemEnum, Variant as SynVariant};
+    use quote::quote;
+    use syn::{
+        Error, Expr, Fields, Ident, ItemEnum, LitStr, TypePath,
+        Variant as SynVariant
+    };
 
     pub type Variants<'a> = Vec<Variant<'a>>;
 
@@ -30,7 +104,7 @@ pub mod common {
         let mut variants = Vec::with_capacity(e.variants.len());
         for SynVariant { ident, discriminant, .. } in e.variants.iter() {
             match discriminant.as_ref() {
-                Some(d) => variants.push(Variant { ident: &ident, expr: &d.1 }),
+                Some(d) => variants.push(Variant { ident, expr: &d.1 }),
                 None => return Err(Error::new(
                     ident.span(),
                     "explicit discriminant value is required"
@@ -39,6 +113,64 @@ pub mod common {
         }
         Ok(variants)
     }
+
+    /// Names of the variants, in declaration order.
+    pub fn get_names(e: &ItemEnum) -> Result<Vec<LitStr>, Error> {
+        let mut names = Vec::with_capacity(e.variants.len());
+        for SynVariant { ident, attrs, .. } in e.variants.iter() {
+            let mut name = None;
+            for attr in attrs.iter().filter(|a| a.path().is_ident("int_enum")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("name") {
+                        name = Some(meta.value()?.parse::<LitStr>()?);
+                        Ok(())
+                    } else {
+                        Err(meta.error("unsupported int_enum variant option"))
+                    }
+                })?;
+            }
+            names.push(name.unwrap_or_else(
+                || LitStr::new(&ident.to_string().to_lowercase(), ident.span())
+            ));
+        }
+        Ok(names)
+    }
+
+    /// Separates the `Unknown(int)` variant from the others.
+    pub fn split_unknown(
+        e: &ItemEnum,
+        t: &TypePath
+    ) -> Result<(ItemEnum, Option<Ident>), Error> {
+        let mut known = e.clone();
+        known.variants = e.variants.iter()
+            .filter(|v| v.ident != "Unknown")
+            .cloned()
+            .collect();
+        match e.variants.iter().find(|v| v.ident == "Unknown") {
+            Some(v) if v.fields.len() == 1
+                && matches!(v.fields, Fields::Unnamed(_))
+                && v.discriminant.is_none() =>
+                Ok((known, Some(v.ident.clone()))),
+            _ => Err(Error::new(
+                e.ident.span(),
+                format!("the unknown option requires an Unknown({}) variant", quote!(#t))
+            ))
+        }
+    }
+
+    /// The enum as it is emitted: without the attributes of the macro and,
+    /// when it has an unknown variant, without the discriminants, which
+    /// data-carrying enums can't have.
+    pub fn strip(e: &ItemEnum, unknown: bool) -> ItemEnum {
+        let mut e = e.clone();
+        for v in e.variants.iter_mut() {
+            v.attrs.retain(|a| !a.path().is_ident("int_enum"));
+            if unknown {
+                v.discriminant = None;
+            }
+        }
+        e
+    }
 }
 
 pub mod impl_from_enum {
@@ -50,10 +182,11 @@ pub mod impl_from_enum {
     pub fn r#impl(
         enum_ident: &Ident,
         enum_variants: &Variants,
-        dest_type: &TypePath
+        dest_type: &TypePath,
+        unknown: Option<&Ident>
     ) -> TokenStream {
         let from = from(enum_ident);
-        let from_ref = from_ref(enum_ident, enum_variants);
+        let from_ref = from_ref(enum_ident, enum_variants, unknown);
         quote! {
             impl ::core::convert::From<#enum_ident> for #dest_type { #from }
             impl ::core::convert::From<&#enum_ident> for #dest_type { #from_ref }
@@ -67,11 +200,15 @@ pub mod impl_from_enum {
     fn from_ref(
         enum_ident: &Ident,
         enum_variants: &Variants,
+        unknown: Option<&Ident>
     ) -> TokenStream {
         let cases = enum_variants.iter().map(
             |Variant { ident, expr }| quote! { #enum_ident::#ident => #expr }
         );
-        quote! { fn from(x: &#enum_ident) -> Self { match x { #(#cases),* } } }
+        let unknown = unknown.map(|u| quote! { , #enum_ident::#u(x) => *x });
+        quote! {
+            fn from(x: &#enum_ident) -> Self { match x { #(#cases),* #unknown } }
+        }
     }
 }
 
@@ -116,3 +253,156 @@ pub mod impl_try_from_type {
         }
     }
 }
+
+pub mod impl_from_type {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{Ident, TypePath};
+    use crate::int_enum::common::{Variant, Variants};
+
+    pub fn r#impl(
+        enum_ident: &Ident,
+        enum_variants: &Variants,
+        dest_type: &TypePath,
+        unknown: &Ident
+    ) -> TokenStream {
+        let cases = enum_variants.iter().map(
+            |Variant { ident, expr }| quote! { #expr => #enum_ident::#ident }
+        );
+        quote! {
+            impl ::core::convert::From<#dest_type> for #enum_ident {
+                fn from(x: #dest_type) -> Self {
+                    match x {
+                        #(#cases),*,
+                        _ => #enum_ident::#unknown(x)
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub mod impl_display {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{Ident, LitStr};
+    use crate::int_enum::common::{Variant, Variants};
+
+    pub fn r#impl(
+        enum_ident: &Ident,
+        enum_variants: &Variants,
+        names: &[LitStr],
+        unknown: Option<&Ident>
+    ) -> TokenStream {
+        let cases = enum_variants.iter().zip(names).map(
+            |(Variant { ident, .. }, name)|
+            quote! { #enum_ident::#ident => f.write_str(#name) }
+        );
+        // Unknown values are printed as numbers, which FromStr parses back.
+        let unknown = unknown.map(
+            |u| quote! { , #enum_ident::#u(x) => ::core::fmt::Display::fmt(x, f) }
+        );
+        quote! {
+            impl ::core::fmt::Display for #enum_ident {
+                fn fmt(
+                    &self,
+                    f: &mut ::core::fmt::Formatter<'_>
+                ) -> ::core::fmt::Result {
+                    match self { #(#cases),* #unknown }
+                }
+            }
+        }
+    }
+}
+
+pub mod impl_from_str {
+    use proc_macro2::TokenStream;
+    use quote::quote;
+    use syn::{Ident, LitStr, TypePath};
+    use crate::int_enum::common::{Variant, Variants};
+
+    pub fn r#impl(
+        enum_ident: &Ident,
+        enum_variants: &Variants,
+        names: &[LitStr],
+        int_type: &TypePath,
+        unknown: Option<&Ident>
+    ) -> TokenStream {
+        let cases = enum_variants.iter().zip(names).map(
+            |(Variant { ident, .. }, name)|
+            quote! { #name => ::core::result::Result::Ok(#enum_ident::#ident) }
+        );
+        let error = quote! {
+            ::std::format!("unknown {} {:?}", ::core::stringify!(#enum_ident), s)
+        };
+        let fallback = match unknown {
+            Some(_) => quote! {
+                _ => s.parse::<#int_type>()
+                    .map(#enum_ident::from)
+                    .map_err(|_| #error)
+            },
+            None => quote! { _ => ::core::result::Result::Err(#error) },
+        };
+        quote! {
+            impl ::core::str::FromStr for #enum_ident {
+                type Err = ::std::string::String;
+
+                fn from_str(
+                    s: &::core::primitive::str
+                ) -> ::core::result::Result<Self, Self::Err> {
+                    match s { #(#cases),*, #fallback }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn error(o: Options, e: ItemEnum) -> String {
+        r#impl(&e, &o).expect_err("expected a compile error").to_string()
+    }
+
+    #[test]
+    fn missing_unknown() {
+        let e = parse_quote! { enum Kind { File = 1 } };
+        assert_eq!(
+            error(parse_quote!(u16, unknown), e),
+            "the unknown option requires an Unknown(u16) variant"
+        );
+    }
+
+    #[test]
+    fn malformed_unknown() {
+        let es: [ItemEnum; 4] = [
+            parse_quote! { enum Kind { File = 1, Unknown } },
+            parse_quote! { enum Kind { File = 1, Unknown(u16, u16) } },
+            parse_quote! { enum Kind { File = 1, Unknown { x: u16 } } },
+            parse_quote! { enum Kind { File = 1, Unknown(u16) = 0 } },
+        ];
+        for e in es {
+            assert_eq!(
+                error(parse_quote!(u16, unknown), e),
+                "the unknown option requires an Unknown(u16) variant"
+            );
+        }
+    }
+
+    #[test]
+    fn missing_discriminant() {
+        let e = parse_quote! { enum Kind { File = 1, Directory } };
+        assert_eq!(error(parse_quote!(u8), e), "explicit discriminant value is required");
+    }
+
+    #[test]
+    fn unsupported_options() {
+        let o: syn::Result<Options> = syn::parse_str("u8, unknwon");
+        assert_eq!(o.err().unwrap().to_string(), "unsupported int_enum option");
+        let e = parse_quote! { enum Kind { #[int_enum(title = "f")] File = 1 } };
+        assert_eq!(error(parse_quote!(u8), e), "unsupported int_enum variant option");
+    }
+}
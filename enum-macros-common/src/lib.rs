@@ -1 +1,2 @@
+pub mod bitflags;
 pub mod int_enum;
@@ -1,12 +1,91 @@
 use proc_macro::TokenStream;
 use syn::{ItemEnum, TypePath, parse_macro_input};
 
+use enum_macros_common::bitflags;
 use enum_macros_common::int_enum;
+use enum_macros_common::int_enum::options::Options;
 
+/// Converts a fieldless enum with explicit discriminants from and to an
+/// integer type: `#[int_enum(u8)]`. Options follow the type, e.g.
+/// `#[int_enum(u8, unknown, display, from_str)]`; see Options.
+///
+/// ```
+/// # use enum_macros::int_enum;
+/// #[derive(Debug, PartialEq)]
+/// #[int_enum(u16, unknown, display, from_str)]
+/// enum Kind {
+///     File = 1,
+///     #[int_enum(name = "block_device")]
+///     BlockDevice = 2,
+///     Unknown(u16),
+/// }
+///
+/// assert_eq!(Kind::from(7), Kind::Unknown(7));
+/// assert_eq!(Kind::BlockDevice.to_string(), "block_device");
+/// assert_eq!("7".parse(), Ok(Kind::Unknown(7)));
+/// ```
+///
+/// The unknown option requires the `Unknown` variant:
+///
+/// ```compile_fail
+/// # use enum_macros::int_enum;
+/// #[int_enum(u16, unknown)]
+/// enum Kind {
+///     File = 1,
+/// }
+/// ```
+///
+/// which holds the integer type, and nothing else:
+///
+/// ```compile_fail
+/// # use enum_macros::int_enum;
+/// #[int_enum(u16, unknown)]
+/// enum Kind {
+///     File = 1,
+///     Unknown(u16, u16),
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn int_enum(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let o = parse_macro_input!(attr as Options);
+    let e = parse_macro_input!(input as ItemEnum);
+
+    int_enum::int_enum(&e, &o).into()
+}
+
+/// Turns a fieldless enum whose discriminants are single bits into a set of
+/// flags backed by an integer type: `#[bitflags(u32)]`. The variants become
+/// associated constants named in SCREAMING_SNAKE_CASE, and unknown bits are
+/// preserved. Clone, Copy, Default, Hash, PartialEq, Eq and Debug are
+/// implemented, so they mustn't be derived.
+///
+/// ```
+/// # use enum_macros::bitflags;
+/// #[bitflags(u32)]
+/// enum Flags {
+///     UniqueName = 0x1,
+///     UniqueNameType = 0x2,
+/// }
+///
+/// let flags = Flags::from_bits_retain(0x9);
+/// assert_eq!(format!("{:?}", flags), "Flags(UNIQUE_NAME | 0x8)");
+/// assert_eq!(Flags::try_from(0x9), Err(0x9));
+/// ```
+///
+/// Each discriminant must be a single bit, which is checked at compile time:
+///
+/// ```compile_fail
+/// # use enum_macros::bitflags;
+/// #[bitflags(u32)]
+/// enum Flags {
+///     UniqueName = 0x1,
+///     Both = 0x3,
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn bitflags(attr: TokenStream, input: TokenStream) -> TokenStream {
     let t = parse_macro_input!(attr as TypePath);
     let e = parse_macro_input!(input as ItemEnum);
 
-    int_enum::int_enum(&e, &t).into()
+    bitflags::bitflags(&e, &t).into()
 }
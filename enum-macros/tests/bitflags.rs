@@ -0,0 +1,59 @@
+use enum_macros::bitflags;
+
+#[bitflags(u32)]
+enum Flags {
+    UniqueName = 0x1,
+    UniqueNameType = 0x2,
+    MMPValid = 0x4,
+}
+
+#[test]
+fn names() {
+    let names: Vec<&str> = Flags::NAMED.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["UNIQUE_NAME", "UNIQUE_NAME_TYPE", "MMP_VALID"]);
+    assert_eq!(Flags::MMP_VALID.bits(), 0x4);
+}
+
+#[test]
+fn debug() {
+    assert_eq!(format!("{:?}", Flags::UNIQUE_NAME), "Flags(UNIQUE_NAME)");
+    assert_eq!(
+        format!("{:?}", Flags::UNIQUE_NAME | Flags::MMP_VALID),
+        "Flags(UNIQUE_NAME | MMP_VALID)"
+    );
+    assert_eq!(format!("{:?}", Flags::from_bits_retain(0x9)), "Flags(UNIQUE_NAME | 0x8)");
+    assert_eq!(format!("{:?}", Flags::from_bits_retain(0x30)), "Flags(0x30)");
+    assert_eq!(format!("{:?}", Flags::empty()), "Flags(0x0)");
+}
+
+#[test]
+fn unknown_bits_are_kept() {
+    let flags = Flags::from_bits_retain(0x81);
+    assert_eq!(flags.bits(), 0x81);
+    assert_eq!(flags.unknown().bits(), 0x80);
+    assert!(flags.contains(Flags::UNIQUE_NAME));
+    assert_eq!(u32::from(flags), 0x81);
+    assert_eq!(flags.iter().collect::<Vec<_>>(), [Flags::UNIQUE_NAME]);
+}
+
+#[test]
+fn try_from() {
+    assert_eq!(Flags::try_from(0x3), Ok(Flags::UNIQUE_NAME | Flags::UNIQUE_NAME_TYPE));
+    assert_eq!(Flags::try_from(0x0), Ok(Flags::empty()));
+    assert_eq!(Flags::try_from(0x9), Err(0x9));
+}
+
+#[test]
+fn set_operations() {
+    let mut flags = Flags::empty();
+    assert!(flags.is_empty());
+    flags.insert(Flags::UNIQUE_NAME_TYPE);
+    flags |= Flags::MMP_VALID;
+    assert!(flags.intersects(Flags::MMP_VALID | Flags::UNIQUE_NAME));
+    assert!(!flags.contains(Flags::MMP_VALID | Flags::UNIQUE_NAME));
+    flags.remove(Flags::MMP_VALID);
+    assert_eq!(flags, Flags::UNIQUE_NAME_TYPE);
+    assert_eq!(flags & Flags::all(), Flags::UNIQUE_NAME_TYPE);
+    assert_eq!(Flags::all().bits(), 0x7);
+    assert_eq!(Flags::all().iter().collect::<Flags>(), Flags::all());
+}
@@ -0,0 +1,75 @@
+use enum_macros::int_enum;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[int_enum(u8)]
+enum Strict {
+    A = 1,
+    B = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[int_enum(u16, unknown, display, from_str)]
+enum Kind {
+    File = 1,
+    #[int_enum(name = "block_device")]
+    BlockDevice = 2,
+    Unknown(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[int_enum(i32, display, from_str)]
+enum Sign {
+    Minus = -1,
+    Plus = 1,
+}
+
+#[test]
+fn try_from() {
+    assert_eq!(Strict::try_from(2), Ok(Strict::B));
+    assert_eq!(Strict::try_from(3), Err(3));
+    assert_eq!(u8::from(Strict::A), 1);
+    assert_eq!(u8::from(&Strict::B), 2);
+}
+
+#[test]
+fn unknown() {
+    assert_eq!(Kind::from(1), Kind::File);
+    assert_eq!(Kind::from(7), Kind::Unknown(7));
+    assert_eq!(u16::from(Kind::Unknown(7)), 7);
+    assert_eq!(u16::from(Kind::BlockDevice), 2);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Kind::File.to_string(), "file");
+    assert_eq!(Kind::BlockDevice.to_string(), "block_device");
+    assert_eq!(Kind::Unknown(300).to_string(), "300");
+    assert_eq!(Sign::Minus.to_string(), "minus");
+}
+
+#[test]
+fn from_str() {
+    assert_eq!("file".parse(), Ok(Kind::File));
+    assert_eq!("block_device".parse(), Ok(Kind::BlockDevice));
+    // The name replaces the default one.
+    assert!("blockdevice".parse::<Kind>().is_err());
+    assert_eq!("plus".parse(), Ok(Sign::Plus));
+    assert_eq!("zero".parse::<Sign>(), Err("unknown Sign \"zero\"".to_string()));
+}
+
+// Unknown values are printed as numbers, which parse back to them. Numbers
+// of known values parse to the known variant.
+#[test]
+fn numeric_fallback() {
+    assert_eq!("300".parse(), Ok(Kind::Unknown(300)));
+    assert_eq!("2".parse(), Ok(Kind::BlockDevice));
+    assert_eq!("70000".parse::<Kind>(), Err("unknown Kind \"70000\"".to_string()));
+    assert!("1".parse::<Sign>().is_err());
+}
+
+#[test]
+fn display_round_trip() {
+    for x in [Kind::File, Kind::BlockDevice, Kind::Unknown(0), Kind::Unknown(u16::MAX)] {
+        assert_eq!(x.to_string().parse(), Ok(x));
+    }
+}
@@ -0,0 +1,202 @@
+#!/usr/bin/env python3
+"""Packs the nvlist fixtures, independently of the nvlist crate.
+
+Writes the same nvlist with the XDR and native encodings, each in both byte
+orders, following the layouts of libnvpair (nvs_xdr_* and nvs_native_*).
+Chars are zero extended in XDR, as done by the kernel's xdrmem_enc_char().
+
+Run from this directory: python3 pack.py
+"""
+
+import struct
+
+BOOLEAN, BYTE, INT16, UINT16, INT32, UINT32, INT64, UINT64, STRING = range(1, 10)
+(BYTE_ARRAY, INT16_ARRAY, UINT16_ARRAY, INT32_ARRAY, UINT32_ARRAY, INT64_ARRAY,
+ UINT64_ARRAY, STRING_ARRAY, HRTIME, NVLIST, NVLIST_ARRAY, BOOLEAN_VALUE, INT8,
+ UINT8, BOOLEAN_ARRAY, INT8_ARRAY, UINT8_ARRAY, DOUBLE) = range(10, 28)
+
+NV_UNIQUE_NAME = 1
+
+SCALARS = {
+    BOOLEAN_VALUE: 'i', BYTE: 'B', INT8: 'b', UINT8: 'B', INT16: 'h',
+    UINT16: 'H', INT32: 'i', UINT32: 'I', INT64: 'q', UINT64: 'Q',
+    HRTIME: 'q', DOUBLE: 'd',
+}
+ARRAYS = {
+    BOOLEAN_ARRAY: 'i', INT8_ARRAY: 'b', UINT8_ARRAY: 'B', INT16_ARRAY: 'h',
+    UINT16_ARRAY: 'H', INT32_ARRAY: 'i', UINT32_ARRAY: 'I', INT64_ARRAY: 'q',
+    UINT64_ARRAY: 'Q',
+}
+# Types narrower than 32 bits are widened by XDR, signed ones sign extended.
+XDR_FORMATS = {'b': 'I', 'B': 'I', 'h': 'i', 'H': 'I'}
+
+
+def nvlist(flags, pairs):
+    return (flags, pairs)
+
+
+def elements(t, v):
+    if t == BOOLEAN:
+        return 0
+    if t in ARRAYS or t in (BYTE_ARRAY, STRING_ARRAY, NVLIST_ARRAY):
+        return len(v)
+    return 1
+
+
+# XDR
+
+def xdr_string(s):
+    return struct.pack('>I', len(s)) + s + b'\0' * (-len(s) % 4)
+
+
+def xdr_scalar(fmt, x):
+    if fmt == 'b':
+        x &= 0xff
+    return struct.pack('>' + XDR_FORMATS.get(fmt, fmt), x)
+
+
+def xdr_value(t, v):
+    if t == BOOLEAN:
+        return b''
+    if t in SCALARS:
+        return xdr_scalar(SCALARS[t], v)
+    if t == STRING:
+        return xdr_string(v)
+    if t == BYTE_ARRAY:
+        return v + b'\0' * (-len(v) % 4)
+    if t in ARRAYS:
+        return struct.pack('>I', len(v)) + b''.join(xdr_scalar(ARRAYS[t], x) for x in v)
+    if t == STRING_ARRAY:
+        return b''.join(xdr_string(x) for x in v)
+    if t == NVLIST:
+        return xdr_nvlist(v)
+    if t == NVLIST_ARRAY:
+        return b''.join(xdr_nvlist(x) for x in v)
+    raise ValueError(t)
+
+
+def xdr_nvlist(nvl):
+    flags, pairs = nvl
+    out = struct.pack('>iI', 0, flags)
+    for name, t, v in pairs:
+        body = xdr_string(name) + struct.pack('>ii', t, elements(t, v)) + xdr_value(t, v)
+        out += struct.pack('>ii', 8 + len(body), native_pair_size(name, t, v)) + body
+    return out + struct.pack('>ii', 0, 0)
+
+
+# Native
+
+NVPAIR_HEADER_SIZE = 16
+NVLIST_SIZE = 24
+POINTER_SIZE = 8
+
+
+def align(n):
+    return (n + 7) & ~7
+
+
+def pad(b):
+    return b + b'\0' * (align(len(b)) - len(b))
+
+
+def native_value(e, t, v):
+    if t == BOOLEAN:
+        return b''
+    if t in SCALARS:
+        return struct.pack(e + SCALARS[t], v)
+    if t == STRING:
+        return v + b'\0'
+    if t == BYTE_ARRAY:
+        return v
+    if t in ARRAYS:
+        return b''.join(struct.pack(e + ARRAYS[t], x) for x in v)
+    if t == STRING_ARRAY:
+        return b'\0' * POINTER_SIZE * len(v) + b''.join(x + b'\0' for x in v)
+    if t == NVLIST:
+        return nvlist_t(e, v)
+    if t == NVLIST_ARRAY:
+        return b'\0' * POINTER_SIZE * len(v) + b''.join(nvlist_t(e, x) for x in v)
+    raise ValueError(t)
+
+
+# The in-memory only fields, nvl_priv and nvl_flag, are left zeroed.
+def nvlist_t(e, nvl):
+    flags, _ = nvl
+    return struct.pack(e + 'iI', 0, flags) + b'\0' * (NVLIST_SIZE - 8)
+
+
+def native_pair_size(name, t, v):
+    value = native_value('>', t, v)
+    return align(NVPAIR_HEADER_SIZE + len(name) + 1) + align(len(value))
+
+
+def native_pairs(e, nvl):
+    _, pairs = nvl
+    out = b''
+    for name, t, v in pairs:
+        header = struct.pack(e + 'ihhii', native_pair_size(name, t, v),
+                             len(name) + 1, 0, elements(t, v), t)
+        out += pad(header + name + b'\0') + pad(native_value(e, t, v))
+        # Embedded nvlists follow the nvpair holding them.
+        if t == NVLIST:
+            out += native_pairs(e, v)
+        elif t == NVLIST_ARRAY:
+            out += b''.join(native_pairs(e, x) for x in v)
+    return out + b'\0' * 4
+
+
+def native_nvlist(e, nvl):
+    flags, _ = nvl
+    return struct.pack(e + 'iI', 0, flags) + native_pairs(e, nvl)
+
+
+# A vdev label, plus a pair of each type and names that aren't UTF-8.
+child = nvlist(NV_UNIQUE_NAME, [
+    (b'type', STRING, b'file'),
+    (b'guid', UINT64, 0x1c0ffee),
+    (b'path', STRING, b'/var/tmp/disk0'),
+])
+NVLIST_FIXTURE = nvlist(NV_UNIQUE_NAME, [
+    (b'version', UINT64, 5000),
+    (b'name', STRING, b'tank'),
+    (b'txg', UINT64, 4),
+    (b'pool_guid', UINT64, 0xdeadbeefcafe),
+    (b'vdev_tree', NVLIST, nvlist(NV_UNIQUE_NAME, [
+        (b'type', STRING, b'mirror'),
+        (b'children', NVLIST_ARRAY, [child, nvlist(NV_UNIQUE_NAME, [])]),
+    ])),
+    (b'features_for_read', NVLIST, nvlist(NV_UNIQUE_NAME, [
+        (b'com.delphix:hole_birth', BOOLEAN, None),
+    ])),
+    (b'boolean_value', BOOLEAN_VALUE, 1),
+    (b'byte', BYTE, 0xab),
+    (b'int8', INT8, -2),
+    (b'uint8', UINT8, 200),
+    (b'int16', INT16, -300),
+    (b'uint16', UINT16, 60000),
+    (b'int32', INT32, -70000),
+    (b'uint32', UINT32, 4000000000),
+    (b'int64', INT64, -5),
+    (b'hrtime', HRTIME, 123456789),
+    (b'double', DOUBLE, 0.5),
+    (b'byte_array', BYTE_ARRAY, b'\x01\x02\x03\x04\x05'),
+    (b'boolean_array', BOOLEAN_ARRAY, [1, 0, 1]),
+    (b'int8_array', INT8_ARRAY, [-1, 2, 3]),
+    (b'uint8_array', UINT8_ARRAY, [255]),
+    (b'int16_array', INT16_ARRAY, [-1, 1]),
+    (b'uint16_array', UINT16_ARRAY, [1, 2, 3]),
+    (b'int32_array', INT32_ARRAY, [-1]),
+    (b'uint32_array', UINT32_ARRAY, []),
+    (b'int64_array', INT64_ARRAY, [-1, 0, 1]),
+    (b'uint64_array', UINT64_ARRAY, [1 << 63]),
+    (b'string_array', STRING_ARRAY, [b'a', b'bcdef', b'']),
+    (b'\xff', UINT64, 1),
+    (b'\xfe', STRING, b'not \xff utf-8'),
+])
+
+if __name__ == '__main__':
+    for encoding, name, pack in ((1, 'xdr', lambda e: xdr_nvlist(NVLIST_FIXTURE)),
+                                 (0, 'native', lambda e: native_nvlist(e, NVLIST_FIXTURE))):
+        for endianness, e, suffix in ((0, '>', 'be'), (1, '<', 'le')):
+            with open(f'{name}_{suffix}.bin', 'wb') as f:
+                f.write(bytes([encoding, endianness, 0, 0]) + pack(e))
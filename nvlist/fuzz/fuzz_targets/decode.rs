@@ -0,0 +1,20 @@
+//! Decodes arbitrary bytes as a packed nvlist: decoding must never panic, and
+//! whatever decodes must survive an encode/decode round trip.
+//!
+//! Run from the nvlist directory with `cargo +nightly fuzz run decode`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use nvlist::Nvlist;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(nvlist) = Nvlist::decode(data) {
+        // Compare encodings rather than nvlists, as NaN doubles are not equal
+        // to themselves. Narrow integers may be truncated by the first decode,
+        // so the first encoding is not compared against the input.
+        let encoded = nvlist.encode().expect("can't encode a decoded nvlist");
+        let decoded = Nvlist::decode(&encoded).expect("can't decode an encoded nvlist");
+        assert_eq!(decoded.encode().expect("can't encode a decoded nvlist"), encoded);
+    }
+});
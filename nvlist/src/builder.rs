@@ -0,0 +1,52 @@
+use xdr::ByteString;
+
+use crate::internal::{Flags, Nvlist, Value};
+
+/// Builds an nvlist in code, adding nvpairs in the order they will be stored.
+///
+/// ```
+/// # use nvlist::Builder;
+/// let vdev_tree = Builder::new()
+///     .add("type", "disk")
+///     .add("guid", 0x2d8e12e1f4a7c3b9u64)
+///     .build();
+/// let label = Builder::new()
+///     .add("name", "tank")
+///     .add("vdev_tree", vdev_tree)
+///     .build();
+/// # assert_eq!(label.get_nvlist("vdev_tree").unwrap().get_str("type").unwrap(), "disk");
+/// ```
+#[derive(Debug)]
+pub struct Builder {
+    nvlist: Nvlist,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder { nvlist: Nvlist::default() }
+    }
+
+    pub fn with_flags(flags: Flags) -> Self {
+        Builder { nvlist: Nvlist::new(flags) }
+    }
+
+    pub fn add(mut self, name: impl Into<ByteString>, value: impl Into<Value>) -> Self {
+        self.nvlist.add(name, value);
+        self
+    }
+
+    /// Adds a `DATA_TYPE_BOOLEAN` nvpair, which carries no value.
+    pub fn add_boolean(self, name: impl Into<ByteString>) -> Self {
+        self.add(name, Value::Boolean)
+    }
+
+    pub fn build(self) -> Nvlist {
+        self.nvlist
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
@@ -0,0 +1,314 @@
+//! Deserialization of nvlists into Rust data structures.
+//!
+//! Nvlists are seen as maps from nvpair names to their values, so they can be
+//! deserialized into structs whose fields are named after the nvpairs:
+//!
+//! ```
+//! # use std::fs::File;
+//! # use binary::Reader;
+//! # use nvlist::{from_nvlist, Nvlist};
+//! # use serde::Deserialize;
+//! #[derive(Deserialize)]
+//! struct VdevTree {
+//!     r#type: Option<String>,
+//!     guid: Option<u64>,
+//!     path: Option<String>,
+//!     #[serde(default)]
+//!     children: Vec<VdevTree>,
+//! }
+//!
+//! # let file = File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/xdr_be.bin"))?;
+//! # let label = Nvlist::read(&mut Reader::new(&file))?;
+//! let vdev_tree: VdevTree = from_nvlist(label.get_nvlist("vdev_tree")?)?;
+//! # assert_eq!(vdev_tree.children[0].path.as_deref(), Some("/var/tmp/disk0"));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, Deserialize, Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use xdr::ByteString;
+
+use crate::internal::{Nvlist, Value};
+
+pub fn from_nvlist<'de, T: Deserialize<'de>>(nvlist: &'de Nvlist) -> Result<T, Error> {
+    T::deserialize(NvlistDeserializer(nvlist))
+}
+
+#[derive(Debug)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+pub struct NvlistDeserializer<'de>(&'de Nvlist);
+
+impl<'de> Deserializer<'de> for NvlistDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut map = MapDeserializer::new(self.0.iter().map(
+            |p| (ByteStringDeserializer(p.name()), ValueDeserializer(p.value()))
+        ));
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for NvlistDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// `DATA_TYPE_BOOLEAN` nvpairs are deserialized as `true`, since their
+/// presence is their value.
+pub struct ValueDeserializer<'de>(&'de Value);
+
+impl<'de> Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Boolean => visitor.visit_bool(true),
+            Value::BooleanValue(x) => visitor.visit_bool(*x),
+            Value::Byte(x) => visitor.visit_u8(*x),
+            Value::Int8(x) => visitor.visit_i8(*x),
+            Value::Uint8(x) => visitor.visit_u8(*x),
+            Value::Int16(x) => visitor.visit_i16(*x),
+            Value::Uint16(x) => visitor.visit_u16(*x),
+            Value::Int32(x) => visitor.visit_i32(*x),
+            Value::Uint32(x) => visitor.visit_u32(*x),
+            Value::Int64(x) => visitor.visit_i64(*x),
+            Value::Uint64(x) => visitor.visit_u64(*x),
+            Value::Hrtime(x) => visitor.visit_i64(*x),
+            Value::Double(x) => visitor.visit_f64(*x),
+            Value::String(x) => ByteStringDeserializer(x).deserialize_any(visitor),
+            Value::ByteArray(x) => visit_seq(visitor, x.iter().copied()),
+            Value::BooleanArray(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Int8Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Uint8Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Int16Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Uint16Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Int32Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Uint32Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Int64Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::Uint64Array(x) => visit_seq(visitor, x.iter().copied()),
+            Value::StringArray(x) => visit_seq(visitor, x.iter().map(ByteStringDeserializer)),
+            Value::Nvlist(x) => NvlistDeserializer(x).deserialize_any(visitor),
+            Value::NvlistArray(x) => visit_seq(visitor, x.iter().map(NvlistDeserializer)),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::ByteArray(x) | Value::Uint8Array(x) => visitor.visit_borrowed_bytes(x),
+            _ => self.deserialize_any(visitor)
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    // A missing nvpair is a missing struct field, so any value is present.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    // Strings name the variants of unit only enums, e.g. the vdev type.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::String(x) => visitor.visit_enum(x.to_string_lossy().into_deserializer()),
+            _ => self.deserialize_any(visitor).map_err(|_: Error| de::Error::custom(
+                format!("expected one of {:?} for enum {}", variants, name)
+            ))
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Names and strings are borrowed when they are UTF-8, and have their invalid
+/// sequences replaced otherwise.
+pub struct ByteStringDeserializer<'de>(&'de ByteString);
+
+impl<'de> Deserializer<'de> for ByteStringDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match std::str::from_utf8(self.0.as_bytes()) {
+            Ok(x) => visitor.visit_borrowed_str(x),
+            Err(_) => visitor.visit_string(self.0.to_string_lossy().into_owned()),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for ByteStringDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn visit_seq<'de, V, I>(visitor: V, items: I) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+    I: Iterator,
+    I::Item: IntoDeserializer<'de, Error>,
+{
+    let mut seq = SeqDeserializer::new(items);
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use binary::Reader;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct VdevTree {
+        r#type: Option<String>,
+        guid: Option<u64>,
+        path: Option<String>,
+        #[serde(default)]
+        children: Vec<VdevTree>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Label {
+        name: String,
+        txg: u64,
+        vdev_tree: VdevTree,
+        features_for_read: Features,
+        #[serde(default)]
+        hostname: Option<String>,
+        byte_array: Vec<u8>,
+        string_array: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Features {
+        #[serde(rename = "com.delphix:hole_birth")]
+        hole_birth: bool,
+    }
+
+    fn decode(name: &str) -> crate::Nvlist {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        crate::Nvlist::read(&mut Reader::new(&File::open(path).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn vdev_tree() {
+        let label = decode("xdr_be.bin");
+        let vdev_tree: VdevTree = from_nvlist(label.get_nvlist("vdev_tree").unwrap()).unwrap();
+        assert_eq!(vdev_tree, VdevTree {
+            r#type: Some("mirror".into()),
+            guid: None,
+            path: None,
+            children: vec![
+                VdevTree {
+                    r#type: Some("file".into()),
+                    guid: Some(0x1c0ffee),
+                    path: Some("/var/tmp/disk0".into()),
+                    children: Vec::new(),
+                },
+                VdevTree { r#type: None, guid: None, path: None, children: Vec::new() },
+            ],
+        });
+    }
+
+    #[test]
+    fn label() {
+        let label: Label = from_nvlist(&decode("native_le.bin")).unwrap();
+        assert_eq!(label.name, "tank");
+        assert_eq!(label.txg, 4);
+        assert_eq!(label.vdev_tree.children.len(), 2);
+        assert!(label.features_for_read.hole_birth);
+        assert_eq!(label.hostname, None);
+        assert_eq!(label.byte_array, [1, 2, 3, 4, 5]);
+        assert_eq!(label.string_array, ["a", "bcdef", ""]);
+    }
+
+    #[test]
+    fn missing_field() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Disk {
+            path: String,
+        }
+        let label = decode("xdr_le.bin");
+        let err = from_nvlist::<Disk>(label.get_nvlist("vdev_tree").unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), "missing field `path`");
+    }
+
+    #[test]
+    fn wrong_type() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Pool {
+            name: u64,
+        }
+        assert!(from_nvlist::<Pool>(&decode("xdr_le.bin")).is_err());
+    }
+}
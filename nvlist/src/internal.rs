@@ -1,49 +1,96 @@
-use binary::Reader;
-use enum_macros::int_enum;
-use std::io::{Error, ErrorKind, Result};
-use xdr::Enum;
+use binary::{Context, Error, Reader, Result, Writer};
+pub use binary::Endianness;
+use enum_macros::{bitflags, int_enum};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use xdr::{
+    Array, Bool, ByteString, Double, Enum, I32, I64, Opaque, Struct, U32, U64
+};
 
-#[derive(Debug)]
+mod native;
+
+/// How deep nvlists can be embedded into each other, the same limit enforced
+/// by libnvpair (nvpair_max_recursion). Deeper nvlists are rejected rather
+/// than overflowing the stack.
+const MAX_DEPTH: usize = 20;
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Nvstream {
     header: StreamHeader,
     nvlist: Nvlist,
 }
 
 impl Nvstream {
+    pub fn new(header: StreamHeader, nvlist: Nvlist) -> Self {
+        Nvstream { header, nvlist }
+    }
+
     pub fn read(r: &mut Reader) -> Result<Self> {
         let header = StreamHeader::read(r)?;
         let nvlist = match header.encoding {
-            Encoding::Native => unimplemented!("Can't read native nvlist"),
-            Encoding::XDR => Nvlist::read(r)?,
+            Encoding::Native => native::read(r, header.endianness)?,
+            Encoding::Xdr => Nvlist::read(r)?,
         };
         Ok(Nvstream { header, nvlist })
     }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        self.header.write(w)?;
+        match self.header.encoding {
+            Encoding::Native => native::write(w, self.header.endianness, &self.nvlist),
+            Encoding::Xdr => self.nvlist.write(w),
+        }
+    }
+
+    pub fn header(&self) -> &StreamHeader {
+        &self.header
+    }
+
+    pub fn nvlist(&self) -> &Nvlist {
+        &self.nvlist
+    }
 }
 
 // Why are Encoding and Endianness not using xdr::Enum? Because they're not
 // XDR-encoded enums: they're 1 byte wide values, while enums in XDR are 32 bit
 // integers. So for now we're keeping this code instead of using the generic
 // implementation. Let's see if it's worth changing this in the furure.
-#[derive(Debug)]
-struct StreamHeader {
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamHeader {
     encoding: Encoding,
     endianness: Endianness,
 }
 
 impl StreamHeader {
+    pub fn new(encoding: Encoding, endianness: Endianness) -> Self {
+        StreamHeader { encoding, endianness }
+    }
+
     fn read(r: &mut Reader) -> Result<Self> {
         let encoding = Encoding::read(r)?;
-        let endianness = Endianness::read(r)?;
+        let endianness = read_endianness(r)?;
         r.skip(2)?; // unused reserved bytes
         Ok(StreamHeader { encoding, endianness })
     }
+
+    fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write(&[u8::from(&self.encoding), encode_endianness(self.endianness), 0, 0])
+    }
+
+    pub fn encoding(&self) -> &Encoding {
+        &self.encoding
+    }
+
+    pub fn endianness(&self) -> &Endianness {
+        &self.endianness
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[int_enum(u8)]
-enum Encoding {
+pub enum Encoding {
     Native = 0,
-    XDR    = 1,
+    Xdr    = 1,
 }
 
 const ENCODING_SIZE: usize = 1;
@@ -51,57 +98,161 @@ const ENCODING_SIZE: usize = 1;
 impl Encoding {
     fn read(r: &mut Reader) -> Result<Self> {
         r.try_read_as::<Self, Error, ENCODING_SIZE>(|x| Self::decode(x[0]))
+            .decoding("nvlist::Encoding")
     }
 
     fn decode(x: u8) -> Result<Self> {
-        match Self::try_from(x) {
-            Ok(v) => Ok(v),
-            Err(n) => Err(Error::new(ErrorKind::InvalidInput, n.to_string()))
-        }
+        Self::try_from(x).map_err(|n| Error::invalid(format!("unknown encoding {}", n)))
     }
 }
 
-#[derive(Debug)]
-#[int_enum(u8)]
-enum Endianness {
-    Big    = 0,
-    Little = 1,
-}
-
 const ENDIANNESS_SIZE: usize = 1;
 
-impl Endianness {
-    fn read(r: &mut Reader) -> Result<Self> {
-        r.try_read_as::<Self, Error, ENDIANNESS_SIZE>(|x| Self::decode(x[0]))
+// The byte order of the stream is a byte of its header, 0 for big endian and
+// 1 for little endian.
+fn read_endianness(r: &mut Reader) -> Result<Endianness> {
+    r.try_read_as::<Endianness, Error, ENDIANNESS_SIZE>(|x| decode_endianness(x[0]))
+        .decoding("nvlist::Endianness")
+}
+
+fn decode_endianness(x: u8) -> Result<Endianness> {
+    match x {
+        0 => Ok(Endianness::Big),
+        1 => Ok(Endianness::Little),
+        n => Err(Error::invalid(format!("unknown endianness {}", n))),
     }
+}
 
-    fn decode(x: u8) -> Result<Self> {
-        match Self::try_from(x) {
-            Ok(v) => Ok(v),
-            Err(n) => Err(Error::new(ErrorKind::InvalidInput, n.to_string()))
-        }
+fn encode_endianness(e: Endianness) -> u8 {
+    match e {
+        Endianness::Big => 0,
+        Endianness::Little => 1,
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Nvlist {
     version: Version,
     flags: Flags,
+    pairs: Vec<Nvpair>,
 }
 
 impl Nvlist {
     pub fn read(r: &mut Reader) -> Result<Self> {
-        let version = Version::read(r)?;
-        let flags = Flags::read(r)?;
-        Ok(Nvlist { version, flags })
+        Self::read_nested(r, 0)
+    }
+
+    fn read_nested(r: &mut Reader, depth: usize) -> Result<Self> {
+        let position = r.position()?;
+        check_depth(depth).at(position)?;
+        let NvlistHeader { version, flags } = NvlistHeader::read(r)?;
+        let flags = Flags::from_bits_retain(flags.into());
+        let mut pairs = Vec::new();
+        while let Some(pair) = Nvpair::read_nested(r, depth)? {
+            pairs.push(pair);
+        }
+        Nvlist::from_pairs(version, flags, pairs)
+    }
+
+    /// Builds a decoded nvlist, rejecting nvpairs that could not have been
+    /// added to it because of its flags.
+    fn from_pairs(version: Version, flags: Flags, pairs: Vec<Nvpair>) -> Result<Self> {
+        let mut names = HashSet::new();
+        for pair in pairs.iter() {
+            let unique = if flags.contains(Flags::UNIQUE_NAME) {
+                names.insert((pair.name().as_bytes(), None))
+            } else if flags.contains(Flags::UNIQUE_NAME_TYPE) {
+                names.insert((pair.name().as_bytes(), Some(pair.value().data_type())))
+            } else {
+                true
+            };
+            if !unique {
+                return Err(Error::invalid("duplicate nvpair")
+                    .field(pair.name().to_string_lossy()))
+            }
+        }
+        Ok(Nvlist { version, flags, pairs })
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        let header = NvlistHeader { version: self.version, flags: self.flags.bits().into() };
+        header.write(w)?;
+        for pair in self.pairs.iter() {
+            pair.write(w)?;
+        }
+        // End of list marker: encoded and decoded sizes set to zero.
+        NvpairSizes { encoded_size: 0.into(), decoded_size: 0.into() }.write(w)
+    }
+
+    /// Size of the nvlist once encoded, end of list marker included.
+    fn encoded_size(&self) -> usize {
+        NVLIST_HEADER_SIZE
+            + self.pairs.iter().map(Nvpair::encoded_size).sum::<usize>()
+            + NVPAIR_SIZES_SIZE
+    }
+
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
+
+    pub fn flags(&self) -> &Flags {
+        &self.flags
+    }
+
+    pub fn pairs(&self) -> &[Nvpair] {
+        &self.pairs
     }
 }
 
-#[derive(Debug, Enum)]
-enum Version { V0 = 0 }
+impl Default for Nvlist {
+    /// An empty nvlist with unique names, as created by
+    /// `nvlist_alloc(&nvl, NV_UNIQUE_NAME, 0)`.
+    fn default() -> Self {
+        Nvlist::new(Flags::UNIQUE_NAME)
+    }
+}
+
+impl Nvlist {
+    pub fn new(flags: Flags) -> Self {
+        Nvlist { version: Version::V0, flags, pairs: Vec::new() }
+    }
+
+    /// Appends an nvpair at the end of the list, after removing the existing
+    /// ones it would clash with according to the nvlist flags.
+    pub fn add(&mut self, name: impl Into<ByteString>, value: impl Into<Value>) {
+        let pair = Nvpair::new(name, value);
+        if self.flags.contains(Flags::UNIQUE_NAME) {
+            self.pairs.retain(|p| p.name != pair.name);
+        } else if self.flags.contains(Flags::UNIQUE_NAME_TYPE) {
+            self.pairs.retain(
+                |p| p.name != pair.name || p.value.data_type() != pair.value.data_type()
+            );
+        }
+        self.pairs.push(pair);
+    }
+
+    /// Removes all the nvpairs with the given name.
+    pub fn remove(&mut self, name: &str) {
+        self.pairs.retain(|p| p.name.as_bytes() != name.as_bytes());
+    }
+}
 
-#[derive(Debug, Enum)]
-enum Flags {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+pub enum Version { V0 = 0 }
+
+#[derive(Struct)]
+struct NvlistHeader {
+    version: Version,
+    flags: U32,
+}
+
+const NVLIST_HEADER_SIZE: usize = 8;
+
+/// Flags of an nvlist (`nvl_nvflag`). This is a bitmask: both flags may be
+/// set, in which case `UNIQUE_NAME` prevails, or neither. Unknown bits are
+/// preserved.
+#[bitflags(u32)]
+pub enum Flags {
     /// Existing nvpairs with matching names are removed before the new nvpair
     /// is added.
     UniqueName = 0x1,
@@ -109,3 +260,487 @@ enum Flags {
     /// the new nvpair is added.
     UniqueNameType = 0x2,
 }
+
+// Sizes of the nvpair in the XDR and native encodings. The decoded size is
+// meant to preallocate the native nvpair and is otherwise ignored.
+#[derive(Struct)]
+struct NvpairSizes {
+    encoded_size: I32,
+    decoded_size: I32,
+}
+
+const NVPAIR_SIZES_SIZE: usize = 8;
+
+/// An nvpair. Its name, like its string values, is kept as stored: they aren't
+/// necessarily UTF-8, e.g. paths written by other systems, and are only
+/// converted, lossily, when looked up or printed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Nvpair {
+    name: ByteString,
+    value: Value,
+}
+
+impl Nvpair {
+    pub fn new(name: impl Into<ByteString>, value: impl Into<Value>) -> Self {
+        Nvpair { name: name.into(), value: value.into() }
+    }
+
+    /// Reads the next nvpair, returning `None` when the end of list marker
+    /// (a pair whose encoded and decoded sizes are both zero) is found.
+    pub fn read(r: &mut Reader) -> Result<Option<Self>> {
+        Self::read_nested(r, 0)
+    }
+
+    fn read_nested(r: &mut Reader, depth: usize) -> Result<Option<Self>> {
+        let NvpairSizes { encoded_size, decoded_size } = NvpairSizes::read(r)?;
+        let (encoded_size, decoded_size) = (i32::from(encoded_size), i32::from(decoded_size));
+        if encoded_size == 0 && decoded_size == 0 {
+            return Ok(None)
+        }
+
+        let name = ByteString::read(r)?;
+        let lossy = name.to_string_lossy();
+        let value = Self::read_value(r, &lossy, depth).field(&lossy)?;
+        Ok(Some(Nvpair { name, value }))
+    }
+
+    fn read_value(r: &mut Reader, name: &str, depth: usize) -> Result<Value> {
+        let position = r.position()?;
+        let data_type: i32 = I32::read(r)?.into();
+        let data_type = DataType::try_from(data_type).map_err(|code| {
+            Error::invalid(UnknownDataType { name: name.to_string(), code }).at(position)
+        })?;
+        let position = r.position()?;
+        let elements: i32 = I32::read(r)?.into();
+        let elements: u32 = elements.try_into().map_err(|_| Error::invalid(
+            format!("negative element count {}", elements)
+        ).at(position))?;
+        Value::read(r, &data_type, elements, depth)
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        NvpairSizes {
+            encoded_size: size(self.encoded_size())?.into(),
+            decoded_size: size(native::pair_size(self))?.into(),
+        }.write(w)?;
+        self.name.write(w)?;
+        I32::from(self.value.data_type() as i32).write(w)?;
+        I32::from(size(self.value.elements())?).write(w)?;
+        self.value.write(w).field(&self.name.to_string_lossy())
+    }
+
+    // The encoded size accounts for the two 4 bytes wide sizes as well, then
+    // the name, the data type and the element count precede the value.
+    fn encoded_size(&self) -> usize {
+        NVPAIR_SIZES_SIZE + string_size(&self.name) + 4 + 4 + self.value.encoded_size()
+    }
+
+    /// The name as stored, which isn't necessarily UTF-8.
+    pub fn name(&self) -> &ByteString {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// Type of the value held by an nvpair, as in `data_type_t`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Enum)]
+pub enum DataType {
+    Boolean        = 1,
+    Byte           = 2,
+    Int16          = 3,
+    Uint16         = 4,
+    Int32          = 5,
+    Uint32         = 6,
+    Int64          = 7,
+    Uint64         = 8,
+    String         = 9,
+    ByteArray      = 10,
+    Int16Array     = 11,
+    Uint16Array    = 12,
+    Int32Array     = 13,
+    Uint32Array    = 14,
+    Int64Array     = 15,
+    Uint64Array    = 16,
+    StringArray    = 17,
+    Hrtime         = 18,
+    Nvlist         = 19,
+    NvlistArray    = 20,
+    BooleanValue   = 21,
+    Int8           = 22,
+    Uint8          = 23,
+    BooleanArray   = 24,
+    Int8Array      = 25,
+    Uint8Array     = 26,
+    Double         = 27,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Boolean,
+    Byte(u8),
+    Int16(i16),
+    Uint16(u16),
+    Int32(i32),
+    Uint32(u32),
+    Int64(i64),
+    Uint64(u64),
+    String(ByteString),
+    ByteArray(Vec<u8>),
+    Int16Array(Vec<i16>),
+    Uint16Array(Vec<u16>),
+    Int32Array(Vec<i32>),
+    Uint32Array(Vec<u32>),
+    Int64Array(Vec<i64>),
+    Uint64Array(Vec<u64>),
+    StringArray(Vec<ByteString>),
+    Hrtime(i64),
+    Nvlist(Nvlist),
+    NvlistArray(Vec<Nvlist>),
+    BooleanValue(bool),
+    Int8(i8),
+    Uint8(u8),
+    BooleanArray(Vec<bool>),
+    Int8Array(Vec<i8>),
+    Uint8Array(Vec<u8>),
+    Double(f64),
+}
+
+impl Value {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Value::Boolean => DataType::Boolean,
+            Value::Byte(_) => DataType::Byte,
+            Value::Int16(_) => DataType::Int16,
+            Value::Uint16(_) => DataType::Uint16,
+            Value::Int32(_) => DataType::Int32,
+            Value::Uint32(_) => DataType::Uint32,
+            Value::Int64(_) => DataType::Int64,
+            Value::Uint64(_) => DataType::Uint64,
+            Value::String(_) => DataType::String,
+            Value::ByteArray(_) => DataType::ByteArray,
+            Value::Int16Array(_) => DataType::Int16Array,
+            Value::Uint16Array(_) => DataType::Uint16Array,
+            Value::Int32Array(_) => DataType::Int32Array,
+            Value::Uint32Array(_) => DataType::Uint32Array,
+            Value::Int64Array(_) => DataType::Int64Array,
+            Value::Uint64Array(_) => DataType::Uint64Array,
+            Value::StringArray(_) => DataType::StringArray,
+            Value::Hrtime(_) => DataType::Hrtime,
+            Value::Nvlist(_) => DataType::Nvlist,
+            Value::NvlistArray(_) => DataType::NvlistArray,
+            Value::BooleanValue(_) => DataType::BooleanValue,
+            Value::Int8(_) => DataType::Int8,
+            Value::Uint8(_) => DataType::Uint8,
+            Value::BooleanArray(_) => DataType::BooleanArray,
+            Value::Int8Array(_) => DataType::Int8Array,
+            Value::Uint8Array(_) => DataType::Uint8Array,
+            Value::Double(_) => DataType::Double,
+        }
+    }
+
+    /// Number of elements, as stored in the nvpair.
+    pub fn elements(&self) -> usize {
+        match self {
+            Value::Boolean => 0,
+            Value::ByteArray(x) => x.len(),
+            Value::Int16Array(x) => x.len(),
+            Value::Uint16Array(x) => x.len(),
+            Value::Int32Array(x) => x.len(),
+            Value::Uint32Array(x) => x.len(),
+            Value::Int64Array(x) => x.len(),
+            Value::Uint64Array(x) => x.len(),
+            Value::StringArray(x) => x.len(),
+            Value::NvlistArray(x) => x.len(),
+            Value::BooleanArray(x) => x.len(),
+            Value::Int8Array(x) => x.len(),
+            Value::Uint8Array(x) => x.len(),
+            _ => 1,
+        }
+    }
+}
+
+/// Generates conversions into the nvpair value holding the given type. Bytes
+/// and unsigned 8 bit integers are left out, as they are ambiguous.
+macro_rules! value_from {
+    ($($type:ty => $variant:ident,)*) => {
+        $(
+            impl From<$type> for Value {
+                fn from(x: $type) -> Self { Value::$variant(x.into()) }
+            }
+        )*
+    };
+}
+
+value_from! {
+    bool => BooleanValue,
+    i8 => Int8,
+    i16 => Int16,
+    u16 => Uint16,
+    i32 => Int32,
+    u32 => Uint32,
+    i64 => Int64,
+    u64 => Uint64,
+    f64 => Double,
+    &str => String,
+    String => String,
+    ByteString => String,
+    Vec<bool> => BooleanArray,
+    Vec<i8> => Int8Array,
+    Vec<i16> => Int16Array,
+    Vec<u16> => Uint16Array,
+    Vec<i32> => Int32Array,
+    Vec<u32> => Uint32Array,
+    Vec<i64> => Int64Array,
+    Vec<u64> => Uint64Array,
+    Vec<ByteString> => StringArray,
+    Nvlist => Nvlist,
+    Vec<Nvlist> => NvlistArray,
+}
+
+impl From<Vec<String>> for Value {
+    fn from(x: Vec<String>) -> Self {
+        Value::StringArray(x.into_iter().map(ByteString::from).collect())
+    }
+}
+
+impl From<Vec<&str>> for Value {
+    fn from(x: Vec<&str>) -> Self {
+        Value::StringArray(x.into_iter().map(ByteString::from).collect())
+    }
+}
+
+// Values narrower than 32 bits are encoded by libnvpair with xdr_char() and
+// xdr_short(), which widen them to a full XDR integer. Decoding truncates them
+// back, just like the C implementation does.
+impl Value {
+    fn read(
+        r: &mut Reader,
+        data_type: &DataType,
+        elements: u32,
+        depth: usize
+    ) -> Result<Self> {
+        let value = match data_type {
+            DataType::Boolean => Value::Boolean,
+            DataType::BooleanValue => Value::BooleanValue(read_bool(r)?),
+            DataType::Byte => Value::Byte(read_i32(r)? as u8),
+            DataType::Int8 => Value::Int8(read_i32(r)? as i8),
+            DataType::Uint8 => Value::Uint8(read_i32(r)? as u8),
+            DataType::Int16 => Value::Int16(read_i32(r)? as i16),
+            DataType::Uint16 => Value::Uint16(read_i32(r)? as u16),
+            DataType::Int32 => Value::Int32(read_i32(r)?),
+            DataType::Uint32 => Value::Uint32(read_u32(r)?),
+            DataType::Int64 => Value::Int64(read_i64(r)?),
+            DataType::Uint64 => Value::Uint64(read_u64(r)?),
+            DataType::Hrtime => Value::Hrtime(read_i64(r)?),
+            DataType::Double => Value::Double(read_f64(r)?),
+            DataType::String => Value::String(ByteString::read(r)?),
+            DataType::ByteArray => Value::ByteArray(read_opaque(r, elements)?),
+            DataType::BooleanArray =>
+                Value::BooleanArray(read_array(r, elements, read_bool)?),
+            DataType::Int8Array =>
+                Value::Int8Array(read_array(r, elements, |r| Ok(read_i32(r)? as i8))?),
+            DataType::Uint8Array =>
+                Value::Uint8Array(read_array(r, elements, |r| Ok(read_i32(r)? as u8))?),
+            DataType::Int16Array =>
+                Value::Int16Array(read_array(r, elements, |r| Ok(read_i32(r)? as i16))?),
+            DataType::Uint16Array =>
+                Value::Uint16Array(read_array(r, elements, |r| Ok(read_i32(r)? as u16))?),
+            DataType::Int32Array =>
+                Value::Int32Array(read_array(r, elements, read_i32)?),
+            DataType::Uint32Array =>
+                Value::Uint32Array(read_array(r, elements, read_u32)?),
+            DataType::Int64Array =>
+                Value::Int64Array(read_array(r, elements, read_i64)?),
+            DataType::Uint64Array =>
+                Value::Uint64Array(read_array(r, elements, read_u64)?),
+            // String arrays are not XDR arrays: libnvpair writes the strings
+            // one after the other, without repeating the element count.
+            DataType::StringArray =>
+                Value::StringArray(read_n(r, elements, ByteString::read)?),
+            DataType::Nvlist => Value::Nvlist(Nvlist::read_nested(r, depth + 1)?),
+            DataType::NvlistArray => Value::NvlistArray(
+                read_n(r, elements, |r| Nvlist::read_nested(r, depth + 1))?
+            ),
+        };
+        Ok(value)
+    }
+}
+
+// Conversely, values narrower than 32 bits are widened: chars are zero
+// extended, shorts are sign extended according to their signedness.
+impl Value {
+    fn write(&self, w: &mut Writer) -> Result<()> {
+        match self {
+            Value::Boolean => Ok(()),
+            Value::BooleanValue(x) => Bool::from(*x).write(w),
+            Value::Byte(x) => U32::from(u32::from(*x)).write(w),
+            Value::Int8(x) => U32::from(u32::from(*x as u8)).write(w),
+            Value::Uint8(x) => U32::from(u32::from(*x)).write(w),
+            Value::Int16(x) => I32::from(i32::from(*x)).write(w),
+            Value::Uint16(x) => U32::from(u32::from(*x)).write(w),
+            Value::Int32(x) => I32::from(*x).write(w),
+            Value::Uint32(x) => U32::from(*x).write(w),
+            Value::Int64(x) => I64::from(*x).write(w),
+            Value::Uint64(x) => U64::from(*x).write(w),
+            Value::Hrtime(x) => I64::from(*x).write(w),
+            Value::Double(x) => Double::from(*x).write(w),
+            Value::String(x) => x.write(w),
+            Value::ByteArray(x) => write_padded(w, x),
+            Value::BooleanArray(x) => write_array(w, x, |x, w| Bool::from(*x).write(w)),
+            Value::Int8Array(x) =>
+                write_array(w, x, |x, w| U32::from(u32::from(*x as u8)).write(w)),
+            Value::Uint8Array(x) => write_array(w, x, |x, w| U32::from(u32::from(*x)).write(w)),
+            Value::Int16Array(x) => write_array(w, x, |x, w| I32::from(i32::from(*x)).write(w)),
+            Value::Uint16Array(x) =>
+                write_array(w, x, |x, w| U32::from(u32::from(*x)).write(w)),
+            Value::Int32Array(x) => write_array(w, x, |x, w| I32::from(*x).write(w)),
+            Value::Uint32Array(x) => write_array(w, x, |x, w| U32::from(*x).write(w)),
+            Value::Int64Array(x) => write_array(w, x, |x, w| I64::from(*x).write(w)),
+            Value::Uint64Array(x) => write_array(w, x, |x, w| U64::from(*x).write(w)),
+            Value::StringArray(x) => write_n(w, x, ByteString::write),
+            Value::Nvlist(x) => x.write(w),
+            Value::NvlistArray(x) => write_n(w, x, Nvlist::write),
+        }
+    }
+
+    fn encoded_size(&self) -> usize {
+        let n = self.elements();
+        match self {
+            Value::Boolean => 0,
+            Value::BooleanValue(_) | Value::Byte(_) | Value::Int8(_) | Value::Uint8(_)
+                | Value::Int16(_) | Value::Uint16(_) | Value::Int32(_)
+                | Value::Uint32(_) => 4,
+            Value::Int64(_) | Value::Uint64(_) | Value::Hrtime(_) | Value::Double(_) => 8,
+            Value::String(x) => string_size(x),
+            Value::ByteArray(_) => padded(n),
+            Value::BooleanArray(_) | Value::Int8Array(_) | Value::Uint8Array(_)
+                | Value::Int16Array(_) | Value::Uint16Array(_) | Value::Int32Array(_)
+                | Value::Uint32Array(_) => 4 + n * 4,
+            Value::Int64Array(_) | Value::Uint64Array(_) => 4 + n * 8,
+            Value::StringArray(xs) => xs.iter().map(string_size).sum(),
+            Value::Nvlist(x) => x.encoded_size(),
+            Value::NvlistArray(xs) => xs.iter().map(Nvlist::encoded_size).sum(),
+        }
+    }
+}
+
+fn read_bool(r: &mut Reader) -> Result<bool> {
+    Ok(Bool::read(r)?.into())
+}
+
+fn read_i32(r: &mut Reader) -> Result<i32> {
+    Ok(I32::read(r)?.into())
+}
+
+fn read_u32(r: &mut Reader) -> Result<u32> {
+    Ok(U32::read(r)?.into())
+}
+
+fn read_i64(r: &mut Reader) -> Result<i64> {
+    Ok(I64::read(r)?.into())
+}
+
+fn read_u64(r: &mut Reader) -> Result<u64> {
+    Ok(U64::read(r)?.into())
+}
+
+fn read_f64(r: &mut Reader) -> Result<f64> {
+    Ok(Double::read(r)?.into())
+}
+
+fn read_opaque(r: &mut Reader, len: u32) -> Result<Vec<u8>> {
+    let len: usize = len.try_into().map_err(Error::invalid)?;
+    Ok(Opaque::read_fixed(r, len)?.into())
+}
+
+fn read_array<T>(
+    r: &mut Reader,
+    elements: u32,
+    f: impl Fn(&mut Reader) -> Result<T>
+) -> Result<Vec<T>> {
+    let position = r.position()?;
+    let xs: Vec<T> = Array::read_max(r, elements, f)?.into();
+    if xs.len() != elements as usize {
+        return Err(Error::invalid(
+            format!("array holds {} elements, expected {}", xs.len(), elements)
+        ).at(position))
+    }
+    Ok(xs)
+}
+
+fn read_n<T>(
+    r: &mut Reader,
+    n: u32,
+    f: impl Fn(&mut Reader) -> Result<T>
+) -> Result<Vec<T>> {
+    let n: usize = n.try_into().map_err(Error::invalid)?;
+    Ok(Array::read_fixed(r, n, f)?.into())
+}
+
+fn check_depth(depth: usize) -> Result<()> {
+    if depth > MAX_DEPTH {
+        return Err(Error::invalid(
+            format!("nvlists nested more than {} levels deep", MAX_DEPTH)
+        ))
+    }
+    Ok(())
+}
+
+// The arrays borrow the elements rather than cloning them, which matters for
+// arrays of nvlists.
+fn write_array<T>(
+    w: &mut Writer,
+    xs: &[T],
+    mut f: impl FnMut(&T, &mut Writer) -> Result<()>
+) -> Result<()> {
+    Array::from(xs.iter().collect::<Vec<_>>()).write(w, |x, w| f(x, w))
+}
+
+fn write_n<T>(
+    w: &mut Writer,
+    xs: &[T],
+    mut f: impl FnMut(&T, &mut Writer) -> Result<()>
+) -> Result<()> {
+    Array::from(xs.iter().collect::<Vec<_>>()).write_fixed(w, |x, w| f(x, w))
+}
+
+// Byte arrays are written from the borrowed bytes, as going through
+// xdr::Opaque would copy them.
+fn write_padded(w: &mut Writer, x: &[u8]) -> Result<()> {
+    w.write(x)?;
+    w.pad((padded(x.len()) - x.len()) as u32)?;
+    Ok(())
+}
+
+fn string_size(x: &ByteString) -> usize {
+    4 + padded(x.as_bytes().len())
+}
+
+// XDR pads variable-length data to a multiple of 4 bytes.
+fn padded(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Sizes and element counts are encoded as signed 32 bit integers.
+fn size(x: usize) -> Result<i32> {
+    x.try_into().map_err(|_| Error::invalid(format!("size {} is too large to encode", x)))
+}
+
+/// Error returned when an nvpair is tagged with a type code that is not part
+/// of `data_type_t`.
+#[derive(Debug)]
+pub struct UnknownDataType {
+    pub name: String,
+    pub code: i32,
+}
+
+impl Display for UnknownDataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "nvpair {:?} has unknown data type {}", self.name, self.code)
+    }
+}
+
+impl std::error::Error for UnknownDataType {}
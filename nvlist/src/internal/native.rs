@@ -0,0 +1,397 @@
+//! Native encoding
+//!
+//! A natively encoded nvlist is a copy of the in-memory representation used
+//! by libnvpair, written with the byte order of the host that packed it.
+//! The top level nvlist starts with its version and flags. Each nvpair is
+//! then laid out as follows, where both the name and the value are padded to
+//! an 8 bytes boundary:
+//!
+//! ```text
+//! +------+---------+---------+-------+------+------+-------+
+//! | size | name_sz | reserve | nelem | type | name | value |
+//! +------+---------+---------+-------+------+------+-------+
+//! <--4--> <---2---> <---2---> <--4--> <--4->
+//! ```
+//!
+//! Embedded nvlists are stored as a bare `nvlist_t` inside the value and
+//! their nvpairs follow the enclosing nvpair. Each list is terminated by 4
+//! zero bytes (a pair of size 0).
+
+use std::io::SeekFrom;
+
+use binary::{Context, Error, Reader, Result, Writer};
+use xdr::ByteString;
+
+use crate::internal::{
+    check_depth, size, DataType, Endianness, Flags, Nvlist, Nvpair,
+    UnknownDataType, Value, Version
+};
+
+const NVPAIR_HEADER_SIZE: usize = 16;
+const NVLIST_SIZE: usize = 24;
+const POINTER_SIZE: usize = 8;
+const ALIGNMENT: usize = 8;
+
+pub fn read(r: &mut Reader, e: Endianness) -> Result<Nvlist> {
+    let position = r.position()?;
+    let header = r.read_as::<[u8; 8], 8>(|x| *x)?;
+    let (version, flags) = decode_nvlist_header(&header, e).at(position)?;
+    read_pairs(r, e, version, flags, 0)
+}
+
+fn read_pairs(
+    r: &mut Reader,
+    e: Endianness,
+    version: Version,
+    flags: Flags,
+    depth: usize
+) -> Result<Nvlist> {
+    let position = r.position()?;
+    check_depth(depth).at(position)?;
+    let mut pairs = Vec::new();
+    loop {
+        let position = r.position()?;
+        let size = r.read_int::<i32, 4>(e)?;
+        if size == 0 {
+            break
+        }
+        let size: usize = match size.try_into() {
+            Ok(s) if s >= NVPAIR_HEADER_SIZE => s,
+            _ => return Err(invalid(format!("invalid nvpair size {}", size)).at(position))
+        };
+        r.seek(SeekFrom::Current(-4))?;
+        let buf = r.read_to(size, |x| x)?;
+        // Errors found within the buffer are located at the start of the
+        // nvpair, those found in embedded nvlists have their own offset.
+        pairs.push(read_pair(r, e, &buf, depth).at(position)?);
+    }
+    Nvlist::from_pairs(version, flags, pairs)
+}
+
+fn read_pair(
+    r: &mut Reader,
+    e: Endianness,
+    buf: &[u8],
+    depth: usize
+) -> Result<Nvpair> {
+    let name_size = e.i16(array(buf, 4)?);
+    let elements = e.i32(array(buf, 8)?);
+    let data_type = e.i32(array(buf, 12)?);
+
+    let name_size: usize = name_size.try_into()
+        .map_err(|_| invalid(format!("invalid nvpair name size {}", name_size)))?;
+    let name = string(slice(buf, NVPAIR_HEADER_SIZE, name_size)?);
+    let lossy = name.to_string_lossy().into_owned();
+    let data_type = DataType::try_from(data_type).map_err(|code| {
+        Error::invalid(UnknownDataType { name: lossy.clone(), code }).field(&lossy)
+    })?;
+    let elements: usize = elements.try_into().map_err(|_| {
+        invalid(format!("negative element count {}", elements)).field(&lossy)
+    })?;
+
+    let data = &buf[align(NVPAIR_HEADER_SIZE + name_size).min(buf.len())..];
+    let value = read_value(r, e, &data_type, elements, data, depth).field(&lossy)?;
+    Ok(Nvpair { name, value })
+}
+
+fn read_value(
+    r: &mut Reader,
+    e: Endianness,
+    data_type: &DataType,
+    elements: usize,
+    data: &[u8],
+    depth: usize
+) -> Result<Value> {
+    let value = match data_type {
+        DataType::Boolean => Value::Boolean,
+        DataType::BooleanValue => Value::BooleanValue(e.i32(array(data, 0)?) != 0),
+        DataType::Byte => Value::Byte(array::<1>(data, 0)?[0]),
+        DataType::Int8 => Value::Int8(i8::from_ne_bytes(array(data, 0)?)),
+        DataType::Uint8 => Value::Uint8(array::<1>(data, 0)?[0]),
+        DataType::Int16 => Value::Int16(e.i16(array(data, 0)?)),
+        DataType::Uint16 => Value::Uint16(e.u16(array(data, 0)?)),
+        DataType::Int32 => Value::Int32(e.i32(array(data, 0)?)),
+        DataType::Uint32 => Value::Uint32(e.u32(array(data, 0)?)),
+        DataType::Int64 => Value::Int64(e.i64(array(data, 0)?)),
+        DataType::Uint64 => Value::Uint64(e.u64(array(data, 0)?)),
+        DataType::Hrtime => Value::Hrtime(e.i64(array(data, 0)?)),
+        DataType::Double => Value::Double(f64::from_bits(e.u64(array(data, 0)?))),
+        DataType::String => Value::String(strings(data, 1)?.remove(0)),
+        DataType::ByteArray =>
+            Value::ByteArray(slice(data, 0, elements)?.to_vec()),
+        DataType::BooleanArray =>
+            Value::BooleanArray(elems(data, elements, |x| e.i32(x) != 0)?),
+        DataType::Int8Array =>
+            Value::Int8Array(elems(data, elements, i8::from_ne_bytes)?),
+        DataType::Uint8Array =>
+            Value::Uint8Array(slice(data, 0, elements)?.to_vec()),
+        DataType::Int16Array =>
+            Value::Int16Array(elems(data, elements, |x| e.i16(x))?),
+        DataType::Uint16Array =>
+            Value::Uint16Array(elems(data, elements, |x| e.u16(x))?),
+        DataType::Int32Array =>
+            Value::Int32Array(elems(data, elements, |x| e.i32(x))?),
+        DataType::Uint32Array =>
+            Value::Uint32Array(elems(data, elements, |x| e.u32(x))?),
+        DataType::Int64Array =>
+            Value::Int64Array(elems(data, elements, |x| e.i64(x))?),
+        DataType::Uint64Array =>
+            Value::Uint64Array(elems(data, elements, |x| e.u64(x))?),
+        // The value begins with an array of pointers, meaningless on disk,
+        // followed by the NUL-terminated strings.
+        DataType::StringArray => {
+            let offset = elements.checked_mul(POINTER_SIZE)
+                .ok_or_else(|| invalid("string array too large"))?;
+            let data = data.get(offset..)
+                .ok_or_else(|| invalid("string array out of bounds"))?;
+            Value::StringArray(strings(data, elements)?)
+        },
+        DataType::Nvlist => {
+            let (version, flags) = decode_nvlist_header(&array(data, 0)?, e)?;
+            Value::Nvlist(read_pairs(r, e, version, flags, depth + 1)?)
+        },
+        // As with string arrays, the nvlist_t headers are preceded by an
+        // array of pointers.
+        DataType::NvlistArray => {
+            let offset = elements.checked_mul(POINTER_SIZE)
+                .ok_or_else(|| invalid("nvlist array too large"))?;
+            // Each element takes a pointer and an nvlist_t in the value, which
+            // bounds how many there can be regardless of the claimed count.
+            let max = data.len() / (POINTER_SIZE + NVLIST_SIZE);
+            let mut nvlists = Vec::with_capacity(elements.min(max));
+            for i in 0..elements {
+                let nvlist = array(data, offset + i * NVLIST_SIZE)
+                    .and_then(|header| decode_nvlist_header(&header, e))
+                    .and_then(|(version, flags)| {
+                        read_pairs(r, e, version, flags, depth + 1)
+                    })
+                    .element(i)?;
+                nvlists.push(nvlist);
+            }
+            Value::NvlistArray(nvlists)
+        },
+    };
+    Ok(value)
+}
+
+pub fn write(w: &mut Writer, e: Endianness, nvlist: &Nvlist) -> Result<()> {
+    let mut buf = Vec::new();
+    encode(&mut buf, e, nvlist)?;
+    w.write(&buf)
+}
+
+// Offsets are aligned relative to the start of the nvlist, hence the buffer.
+fn encode(buf: &mut Vec<u8>, e: Endianness, nvlist: &Nvlist) -> Result<()> {
+    buf.extend(e.encode(nvlist.version as i32));
+    buf.extend(e.encode(nvlist.flags.bits()));
+    write_pairs(buf, e, nvlist)
+}
+
+fn write_pairs(buf: &mut Vec<u8>, e: Endianness, nvlist: &Nvlist) -> Result<()> {
+    for pair in nvlist.pairs.iter() {
+        write_pair(buf, e, pair)?;
+    }
+    buf.extend([0; 4]);
+    Ok(())
+}
+
+fn write_pair(buf: &mut Vec<u8>, e: Endianness, pair: &Nvpair) -> Result<()> {
+    let start = buf.len();
+    let name_size = pair.name.as_bytes().len() + 1;
+    let encoded_name_size = i16::try_from(name_size).map_err(|_| invalid(format!(
+        "nvpair name of {} bytes is too long to encode", name_size
+    )))?;
+    buf.extend(e.encode(size(pair_size(pair))?));
+    buf.extend(e.encode(encoded_name_size));
+    buf.extend([0; 2]); // reserved
+    buf.extend(e.encode(size(pair.value.elements())?));
+    buf.extend(e.encode(pair.value.data_type() as i32));
+    buf.extend(pair.name.as_bytes());
+    buf.push(0);
+    buf.resize(start + align(NVPAIR_HEADER_SIZE + name_size), 0);
+    write_value(buf, e, &pair.value);
+    buf.resize(start + pair_size(pair), 0);
+
+    match &pair.value {
+        Value::Nvlist(x) => write_pairs(buf, e, x),
+        Value::NvlistArray(xs) => xs.iter().try_for_each(|x| write_pairs(buf, e, x)),
+        _ => Ok(())
+    }
+}
+
+fn write_value(buf: &mut Vec<u8>, e: Endianness, value: &Value) {
+    match value {
+        Value::Boolean => (),
+        Value::BooleanValue(x) => buf.extend(e.encode(i32::from(*x))),
+        Value::Byte(x) => buf.push(*x),
+        Value::Int8(x) => buf.extend(x.to_be_bytes()),
+        Value::Uint8(x) => buf.push(*x),
+        Value::Int16(x) => buf.extend(e.encode(*x)),
+        Value::Uint16(x) => buf.extend(e.encode(*x)),
+        Value::Int32(x) => buf.extend(e.encode(*x)),
+        Value::Uint32(x) => buf.extend(e.encode(*x)),
+        Value::Int64(x) => buf.extend(e.encode(*x)),
+        Value::Uint64(x) => buf.extend(e.encode(*x)),
+        Value::Hrtime(x) => buf.extend(e.encode(*x)),
+        Value::Double(x) => buf.extend(e.encode(x.to_bits())),
+        Value::String(x) => {
+            buf.extend(x.as_bytes());
+            buf.push(0);
+        },
+        Value::ByteArray(xs) => buf.extend(xs),
+        Value::BooleanArray(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(i32::from(*x)))),
+        Value::Int8Array(xs) => xs.iter().for_each(|x| buf.extend(x.to_be_bytes())),
+        Value::Uint8Array(xs) => buf.extend(xs),
+        Value::Int16Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::Uint16Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::Int32Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::Uint32Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::Int64Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::Uint64Array(xs) =>
+            xs.iter().for_each(|x| buf.extend(e.encode(*x))),
+        Value::StringArray(xs) => {
+            buf.resize(buf.len() + xs.len() * POINTER_SIZE, 0);
+            for x in xs.iter() {
+                buf.extend(x.as_bytes());
+                buf.push(0);
+            }
+        },
+        Value::Nvlist(x) => write_nvlist_header(buf, e, x),
+        Value::NvlistArray(xs) => {
+            buf.resize(buf.len() + xs.len() * POINTER_SIZE, 0);
+            xs.iter().for_each(|x| write_nvlist_header(buf, e, x));
+        },
+    }
+}
+
+/// Writes an `nvlist_t`, leaving its in-memory only fields zeroed.
+fn write_nvlist_header(buf: &mut Vec<u8>, e: Endianness, nvlist: &Nvlist) {
+    let start = buf.len();
+    buf.extend(e.encode(nvlist.version as i32));
+    buf.extend(e.encode(nvlist.flags.bits()));
+    buf.resize(start + NVLIST_SIZE, 0);
+}
+
+/// Size of an nvpair once unpacked by libnvpair (`nvp_size`), including the
+/// padding of both its name and value.
+pub fn pair_size(pair: &Nvpair) -> usize {
+    align(NVPAIR_HEADER_SIZE + pair.name.as_bytes().len() + 1) + align(value_size(&pair.value))
+}
+
+fn value_size(value: &Value) -> usize {
+    let n = value.elements();
+    match value {
+        Value::Boolean => 0,
+        Value::Byte(_) | Value::Int8(_) | Value::Uint8(_) => 1,
+        Value::Int16(_) | Value::Uint16(_) => 2,
+        Value::BooleanValue(_) | Value::Int32(_) | Value::Uint32(_) => 4,
+        Value::Int64(_) | Value::Uint64(_) | Value::Hrtime(_) | Value::Double(_) => 8,
+        Value::String(x) => x.as_bytes().len() + 1,
+        Value::ByteArray(_) | Value::Int8Array(_) | Value::Uint8Array(_) => n,
+        Value::Int16Array(_) | Value::Uint16Array(_) => n * 2,
+        Value::BooleanArray(_) | Value::Int32Array(_) | Value::Uint32Array(_) => n * 4,
+        Value::Int64Array(_) | Value::Uint64Array(_) => n * 8,
+        Value::StringArray(xs) =>
+            n * POINTER_SIZE + xs.iter().map(|x| x.as_bytes().len() + 1).sum::<usize>(),
+        Value::Nvlist(_) => NVLIST_SIZE,
+        Value::NvlistArray(_) => n * (POINTER_SIZE + NVLIST_SIZE),
+    }
+}
+
+fn decode_nvlist_header(x: &[u8; 8], e: Endianness) -> Result<(Version, Flags)> {
+    let version = e.i32(array(x, 0)?);
+    let flags = e.u32(array(x, 4)?);
+    let version = Version::try_from(version)
+        .map_err(|v| invalid(format!("unsupported nvlist version {}", v)))?;
+    Ok((version, Flags::from_bits_retain(flags)))
+}
+
+fn elems<T, const N: usize>(
+    data: &[u8],
+    elements: usize,
+    f: impl Fn([u8; N]) -> T
+) -> Result<Vec<T>> {
+    let len = elements.checked_mul(N)
+        .ok_or_else(|| invalid("array too large"))?;
+    Ok(slice(data, 0, len)?
+       .chunks_exact(N)
+       .map(|x| f(x.try_into().unwrap()))
+       .collect())
+}
+
+fn strings(mut data: &[u8], n: usize) -> Result<Vec<ByteString>> {
+    let mut strings = Vec::new();
+    for _ in 0..n {
+        let end = data.iter().position(|b| *b == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        strings.push(string(&data[..end]));
+        data = &data[end + 1..];
+    }
+    Ok(strings)
+}
+
+fn string(x: &[u8]) -> ByteString {
+    let x = x.strip_suffix(&[0]).unwrap_or(x);
+    ByteString::from(x.to_vec())
+}
+
+fn array<const N: usize>(buf: &[u8], offset: usize) -> Result<[u8; N]> {
+    Ok(slice(buf, offset, N)?.try_into().unwrap())
+}
+
+fn slice(buf: &[u8], offset: usize, len: usize) -> Result<&[u8]> {
+    offset.checked_add(len)
+        .and_then(|end| buf.get(offset..end))
+        .ok_or_else(|| invalid(format!(
+            "can't read {} bytes at offset {} of a {} bytes nvpair",
+            len, offset, buf.len()
+        )))
+}
+
+fn align(x: usize) -> usize {
+    (x + (ALIGNMENT - 1)) & !(ALIGNMENT - 1)
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::invalid(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Packed by fixtures/pack.py, after a 4 bytes stream header.
+    const BE: &[u8] = include_bytes!("../../fixtures/native_be.bin");
+    const LE: &[u8] = include_bytes!("../../fixtures/native_le.bin");
+
+    fn decode(packed: &[u8], e: Endianness) -> Result<Nvlist> {
+        read(&mut Reader::from_bytes(&packed[4..]), e)
+    }
+
+    #[test]
+    fn both_byte_orders() -> Result<()> {
+        let be = decode(BE, Endianness::Big)?;
+        assert_eq!(be, decode(LE, Endianness::Little)?);
+        assert_eq!(be.get_u64("pool_guid")?, 0xdeadbeefcafe);
+        assert_eq!(be.get_u16("uint16")?, 60000);
+        assert_eq!(be.get_u64_array("uint64_array")?, [1 << 63]);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_byte_order() {
+        assert!(decode(BE, Endianness::Little).is_err());
+        assert!(decode(LE, Endianness::Big).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        assert!(decode(&LE[..LE.len() - 4], Endianness::Little).is_err());
+    }
+}
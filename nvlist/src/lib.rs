@@ -1,4 +1,19 @@
+mod builder;
+#[cfg(feature = "serde")]
+pub mod de;
 pub mod internal;
+mod lookup;
 pub mod nvlist;
+mod print;
+#[cfg(feature = "serde")]
+mod ser;
 
+pub use crate::builder::Builder;
+#[cfg(feature = "serde")]
+pub use crate::de::from_nvlist;
+pub use crate::internal::{DataType, Encoding, Endianness, Flags, Nvpair, Value};
+pub use crate::lookup::LookupError;
 pub use crate::nvlist::Nvlist;
+pub use crate::print::Printer;
+#[cfg(feature = "serde")]
+pub use crate::ser::to_nvlist;
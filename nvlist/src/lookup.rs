@@ -0,0 +1,243 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use std::slice::Iter;
+
+use xdr::ByteString;
+
+use crate::internal::{DataType, Nvlist, Nvpair, Value};
+
+/// Generates a getter for each nvpair data type, returning the value of the
+/// first nvpair with the given name and the expected type.
+macro_rules! getters {
+    ($($getter:ident: $variant:ident($x:ident) => $value:expr, $type:ty;)*) => {
+        $(
+            pub fn $getter(&self, name: &str) -> Result<$type, LookupError> {
+                self.lookup(name, DataType::$variant, |v| match v {
+                    Value::$variant($x) => Some($value),
+                    _ => None
+                })
+            }
+        )*
+    };
+}
+
+impl Nvlist {
+    /// Iterates over the nvpairs in the order they are stored.
+    pub fn iter(&self) -> Iter<'_, Nvpair> {
+        self.pairs().iter()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.iter().find(|p| p.name().as_bytes() == name.as_bytes()).map(Nvpair::value)
+    }
+
+    /// Returns whether the nvlist holds a `DATA_TYPE_BOOLEAN` nvpair with the
+    /// given name. Such nvpairs carry no value: their presence is the value.
+    pub fn get_boolean(&self, name: &str) -> Result<(), LookupError> {
+        self.lookup(name, DataType::Boolean, |v| match v {
+            Value::Boolean => Some(()),
+            _ => None
+        })
+    }
+
+    getters! {
+        get_bool: BooleanValue(x) => *x, bool;
+        get_byte: Byte(x) => *x, u8;
+        get_i8: Int8(x) => *x, i8;
+        get_u8: Uint8(x) => *x, u8;
+        get_i16: Int16(x) => *x, i16;
+        get_u16: Uint16(x) => *x, u16;
+        get_i32: Int32(x) => *x, i32;
+        get_u32: Uint32(x) => *x, u32;
+        get_i64: Int64(x) => *x, i64;
+        get_u64: Uint64(x) => *x, u64;
+        get_hrtime: Hrtime(x) => *x, i64;
+        get_f64: Double(x) => *x, f64;
+        get_str: String(x) => x.to_string_lossy(), Cow<'_, str>;
+        get_byte_string: String(x) => x, &ByteString;
+        get_nvlist: Nvlist(x) => x, &Nvlist;
+        get_bool_array: BooleanArray(x) => x.as_slice(), &[bool];
+        get_byte_array: ByteArray(x) => x.as_slice(), &[u8];
+        get_i8_array: Int8Array(x) => x.as_slice(), &[i8];
+        get_u8_array: Uint8Array(x) => x.as_slice(), &[u8];
+        get_i16_array: Int16Array(x) => x.as_slice(), &[i16];
+        get_u16_array: Uint16Array(x) => x.as_slice(), &[u16];
+        get_i32_array: Int32Array(x) => x.as_slice(), &[i32];
+        get_u32_array: Uint32Array(x) => x.as_slice(), &[u32];
+        get_i64_array: Int64Array(x) => x.as_slice(), &[i64];
+        get_u64_array: Uint64Array(x) => x.as_slice(), &[u64];
+        get_str_array: StringArray(x) =>
+            x.iter().map(ByteString::to_string_lossy).collect(), Vec<Cow<'_, str>>;
+        get_byte_string_array: StringArray(x) => x.as_slice(), &[ByteString];
+        get_nvlist_array: NvlistArray(x) => x.as_slice(), &[Nvlist];
+    }
+
+    // Just like nvlist_lookup_*(), nvpairs are matched by both name and type:
+    // lists without NV_UNIQUE_NAME may hold several nvpairs with the same name.
+    fn lookup<'a, T>(
+        &'a self,
+        name: &str,
+        expected: DataType,
+        f: impl Fn(&'a Value) -> Option<T>
+    ) -> Result<T, LookupError> {
+        let mut found = None;
+        for pair in self.iter().filter(|p| p.name().as_bytes() == name.as_bytes()) {
+            if let Some(x) = f(pair.value()) {
+                return Ok(x)
+            }
+            found.get_or_insert(pair.value().data_type());
+        }
+        let name = name.to_string();
+        match found {
+            Some(found) => Err(LookupError::WrongType { name, expected, found }),
+            None => Err(LookupError::Missing { name }),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Nvlist {
+    type Item = &'a Nvpair;
+    type IntoIter = Iter<'a, Nvpair>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[derive(Debug)]
+pub enum LookupError {
+    /// No nvpair with the given name exists.
+    Missing { name: String },
+    /// Nvpairs with the given name exist, but hold other types of values.
+    WrongType { name: String, expected: DataType, found: DataType },
+}
+
+impl Display for LookupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LookupError::Missing { name } =>
+                write!(f, "nvpair {:?} not found", name),
+            LookupError::WrongType { name, expected, found } =>
+                write!(f, "nvpair {:?} is {:?}, expected {:?}", name, found, expected),
+        }
+    }
+}
+
+impl std::error::Error for LookupError {}
+
+impl From<LookupError> for binary::Error {
+    fn from(e: LookupError) -> Self {
+        binary::Error::invalid(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binary::Result;
+
+    use super::*;
+
+    // An XDR stream holding a DATA_TYPE_BOOLEAN nvpair named "flag" and a
+    // DATA_TYPE_UINT64 one named "txg".
+    const PACKED: &[u8] = &[
+        1, 1, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 24, 0, 0, 0, 24, 0, 0, 0, 4, b'f', b'l', b'a', b'g',
+            0, 0, 0, 1, 0, 0, 0, 0,
+        0, 0, 0, 32, 0, 0, 0, 32, 0, 0, 0, 3, b't', b'x', b'g', 0,
+            0, 0, 0, 8, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 7,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    /// Checks that each getter reports missing nvpairs, and nvpairs of
+    /// another type along with the type it expected.
+    macro_rules! check_getters {
+        ($nvlist:expr, $($getter:ident: $variant:ident,)*) => {
+            $(
+                assert!(matches!(
+                    $nvlist.$getter("missing"),
+                    Err(LookupError::Missing { name }) if name == "missing"
+                ));
+                assert!(matches!(
+                    $nvlist.$getter("flag"),
+                    Err(LookupError::WrongType {
+                        name,
+                        expected: DataType::$variant,
+                        found: DataType::Boolean,
+                    }) if name == "flag"
+                ));
+            )*
+        };
+    }
+
+    fn decode() -> Result<crate::Nvlist> {
+        crate::Nvlist::decode(PACKED)
+    }
+
+    #[test]
+    fn found() -> Result<()> {
+        let nvlist = decode()?;
+        assert!(nvlist.get_boolean("flag").is_ok());
+        assert_eq!(nvlist.get_u64("txg").ok(), Some(7));
+        assert!(nvlist.contains("txg"));
+        assert!(matches!(nvlist.get("txg"), Some(Value::Uint64(7))));
+        assert_eq!(nvlist.iter().count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn boolean() -> Result<()> {
+        let nvlist = decode()?;
+        assert!(matches!(nvlist.get_boolean("missing"), Err(LookupError::Missing { .. })));
+        assert!(matches!(
+            nvlist.get_boolean("txg"),
+            Err(LookupError::WrongType {
+                expected: DataType::Boolean,
+                found: DataType::Uint64,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn getters() -> Result<()> {
+        let nvlist = decode()?;
+        check_getters! {
+            nvlist,
+            get_bool: BooleanValue,
+            get_byte: Byte,
+            get_i8: Int8,
+            get_u8: Uint8,
+            get_i16: Int16,
+            get_u16: Uint16,
+            get_i32: Int32,
+            get_u32: Uint32,
+            get_i64: Int64,
+            get_u64: Uint64,
+            get_hrtime: Hrtime,
+            get_f64: Double,
+            get_str: String,
+            get_byte_string: String,
+            get_nvlist: Nvlist,
+            get_bool_array: BooleanArray,
+            get_byte_array: ByteArray,
+            get_i8_array: Int8Array,
+            get_u8_array: Uint8Array,
+            get_i16_array: Int16Array,
+            get_u16_array: Uint16Array,
+            get_i32_array: Int32Array,
+            get_u32_array: Uint32Array,
+            get_i64_array: Int64Array,
+            get_u64_array: Uint64Array,
+            get_str_array: StringArray,
+            get_byte_string_array: StringArray,
+            get_nvlist_array: NvlistArray,
+        }
+        Ok(())
+    }
+}
@@ -1,16 +1,187 @@
-use std::io::Result;
+use std::ops::Deref;
 
-use binary::Reader;
+use binary::{Reader, Result, Writer};
 
-use crate::internal::Nvstream;
+use crate::internal::{self, Encoding, Endianness, Nvstream, StreamHeader};
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Nvlist {
     nvstream: Nvstream,
 }
 
 impl Nvlist {
+    pub fn new(
+        nvlist: internal::Nvlist,
+        encoding: Encoding,
+        endianness: Endianness
+    ) -> Self {
+        let header = StreamHeader::new(encoding, endianness);
+        Self { nvstream: Nvstream::new(header, nvlist) }
+    }
+
     pub fn read(r: &mut Reader) -> Result<Self> {
         Ok(Self { nvstream: Nvstream::read(r)? })
     }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Self::read(&mut Reader::from_bytes(bytes))
+    }
+
+    /// Packs the nvlist with the encoding and byte order it was read or
+    /// created with.
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        self.nvstream.write(w)
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        Writer::to_vec(|w| self.write(w))
+    }
+}
+
+// Lookups are implemented on the nvlist carried by the stream, so that nested
+// nvlists can be queried in the same way as the top level one.
+impl Deref for Nvlist {
+    type Target = internal::Nvlist;
+
+    fn deref(&self) -> &Self::Target {
+        self.nvstream.nvlist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use xdr::ByteString;
+
+    use super::*;
+    use crate::{Builder, DataType, Value};
+
+    // Packed by fixtures/pack.py.
+    const XDR_BE: &str = "xdr_be.bin";
+    const XDR_LE: &str = "xdr_le.bin";
+    const NATIVE_BE: &str = "native_be.bin";
+    const NATIVE_LE: &str = "native_le.bin";
+
+    fn fixture(name: &str) -> String {
+        format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn decode(name: &str) -> Result<Nvlist> {
+        Nvlist::read(&mut Reader::new(&File::open(fixture(name))?))
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        for name in [XDR_BE, XDR_LE, NATIVE_BE, NATIVE_LE] {
+            assert_eq!(decode(name)?.encode()?, std::fs::read(fixture(name))?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn encodings_agree() -> Result<()> {
+        let xdr = decode(XDR_BE)?;
+        for name in [XDR_LE, NATIVE_BE, NATIVE_LE] {
+            assert_eq!(*decode(name)?, *xdr);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lookups() -> Result<()> {
+        let nvlist = decode(NATIVE_LE)?;
+        assert_eq!(nvlist.get_u64("txg")?, 4);
+        let vdev_tree = nvlist.get_nvlist("vdev_tree")?;
+        let children = vdev_tree.get_nvlist_array("children")?;
+        assert_eq!(children[0].get_str("path")?, "/var/tmp/disk0");
+        assert!(children[1].pairs().is_empty());
+        assert!(nvlist.get_nvlist("features_for_read")?.contains("com.delphix:hole_birth"));
+        assert_eq!(nvlist.get_i8("int8")?, -2);
+        assert_eq!(nvlist.get_i16_array("int16_array")?, [-1, 1]);
+        assert_eq!(nvlist.get_str_array("string_array")?, ["a", "bcdef", ""]);
+        assert_eq!(nvlist.get_byte_string_array("string_array")?.len(), 3);
+        Ok(())
+    }
+
+    // Names that aren't UTF-8 are distinct, even though both print as U+FFFD.
+    #[test]
+    fn names_are_bytes() -> Result<()> {
+        let nvlist = decode(XDR_BE)?;
+        let name = |x: &[u8]| ByteString::from(x.to_vec());
+        let ff = nvlist.iter().find(|p| *p.name() == name(b"\xff")).unwrap();
+        let fe = nvlist.iter().find(|p| *p.name() == name(b"\xfe")).unwrap();
+        assert_eq!(*ff.value(), Value::Uint64(1));
+        assert_eq!(*fe.value(), Value::String(name(b"not \xff utf-8")));
+        // Nor are they looked up by their lossy conversion.
+        assert!(nvlist.get_str("\u{fffd}").is_err());
+        Ok(())
+    }
+
+    // Native nvpairs store the size of their name in 16 bits, XDR ones don't.
+    #[test]
+    fn name_too_long() {
+        let nvlist = Builder::new().add("x".repeat(usize::from(u16::MAX)), 1u64).build();
+        let native = Nvlist::new(nvlist.clone(), Encoding::Native, Endianness::Little);
+        assert!(native.encode().is_err());
+        let xdr = Nvlist::new(nvlist, Encoding::Xdr, Endianness::Little);
+        assert!(xdr.encode().is_ok());
+    }
+
+    // An XDR stream holding a single nvpair named "x", which is cut short
+    // after the given value.
+    fn xdr_pair(data_type: DataType, elements: u32, value: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        bytes.extend([0, 0, 0, 64, 0, 0, 0, 64, 0, 0, 0, 1, b'x', 0, 0, 0]);
+        bytes.extend((data_type as i32).to_be_bytes());
+        bytes.extend(elements.to_be_bytes());
+        bytes.extend(value);
+        bytes
+    }
+
+    fn over_limit(bytes: &[u8], max_alloc: usize) -> bool {
+        let mut r = Reader::from_bytes(bytes).with_max_alloc(max_alloc);
+        matches!(Nvlist::read(&mut r), Err(e) if e.to_string().contains("can't allocate"))
+    }
+
+    #[test]
+    fn hostile_lengths() {
+        let max = u32::MAX >> 1;
+        let hostile = [
+            xdr_pair(DataType::String, 1, &u32::MAX.to_be_bytes()),
+            xdr_pair(DataType::ByteArray, max, &[]),
+            xdr_pair(DataType::Uint64Array, max, &max.to_be_bytes()),
+            xdr_pair(DataType::StringArray, max, &[]),
+            xdr_pair(DataType::NvlistArray, max, &[]),
+        ];
+        for bytes in hostile {
+            assert!(over_limit(&bytes, binary::DEFAULT_MAX_ALLOC));
+        }
+    }
+
+    #[test]
+    fn custom_max_alloc() {
+        let string = xdr_pair(DataType::String, 1, &[0, 0, 0, 5, b'a', b'b', b'c', b'd', b'e']);
+        assert!(over_limit(&string, 4));
+        assert!(!over_limit(&string, 5));
+        let bytes = xdr_pair(DataType::ByteArray, 5, &[1, 2, 3, 4, 5]);
+        assert!(over_limit(&bytes, 4));
+        assert!(!over_limit(&bytes, 5));
+        let array = xdr_pair(DataType::Uint64Array, 2, &[0, 0, 0, 2]);
+        assert!(over_limit(&array, 15));
+        assert!(!over_limit(&array, 16));
+        let nvlists = xdr_pair(DataType::NvlistArray, 2, &[]);
+        assert!(over_limit(&nvlists, 1));
+    }
+
+    #[test]
+    fn truncated() -> Result<()> {
+        for name in [XDR_BE, XDR_LE, NATIVE_BE, NATIVE_LE] {
+            let bytes = std::fs::read(fixture(name))?;
+            for len in 0..bytes.len() {
+                assert!(Nvlist::decode(&bytes[..len]).is_err(), "{} cut at {}", name, len);
+            }
+        }
+        Ok(())
+    }
 }
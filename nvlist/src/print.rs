@@ -0,0 +1,217 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::internal::{Nvlist, Value};
+
+const INDENT: usize = 4;
+
+/// Prints an nvlist with the layout of `zdb -l`: one `name: value` line per
+/// nvpair, with embedded nvlists indented below their name.
+///
+/// ```text
+/// name: 'tank'
+/// txg: 4
+/// vdev_tree:
+///     type: 'mirror'
+///     children[0]:
+///         type: 'disk'
+/// features_for_read:
+///     com.delphix:hole_birth
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Printer<'a> {
+    nvlist: &'a Nvlist,
+    indent: usize,
+    hex_guids: bool,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(nvlist: &'a Nvlist) -> Self {
+        Printer { nvlist, indent: 0, hex_guids: false }
+    }
+
+    /// Indents every line by the given number of spaces.
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Prints `guid` and `*_guid` nvpairs in hexadecimal.
+    pub fn hex_guids(mut self, hex_guids: bool) -> Self {
+        self.hex_guids = hex_guids;
+        self
+    }
+
+    fn print(&self, f: &mut Formatter<'_>, nvlist: &Nvlist, indent: usize) -> Result {
+        for pair in nvlist.iter() {
+            let name = pair.name().to_string_lossy();
+            match pair.value() {
+                Value::Boolean => writeln!(f, "{:indent$}{}", "", name)?,
+                Value::Uint64(x) if self.hex_guids && is_guid(&name) =>
+                    writeln!(f, "{:indent$}{}: {:#x}", "", name, x)?,
+                Value::Nvlist(x) => {
+                    writeln!(f, "{:indent$}{}:", "", name)?;
+                    self.print(f, x, indent + INDENT)?;
+                },
+                Value::NvlistArray(xs) => {
+                    for (i, x) in xs.iter().enumerate() {
+                        writeln!(f, "{:indent$}{}[{}]:", "", name, i)?;
+                        self.print(f, x, indent + INDENT)?;
+                    }
+                },
+                value => {
+                    write!(f, "{:indent$}{}: ", "", name)?;
+                    print_value(f, value)?;
+                    writeln!(f)?;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Printer<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.print(f, self.nvlist, self.indent)
+    }
+}
+
+impl Nvlist {
+    pub fn printer(&self) -> Printer<'_> {
+        Printer::new(self)
+    }
+}
+
+impl Display for Nvlist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.printer().fmt(f)
+    }
+}
+
+impl Display for crate::Nvlist {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        (**self).fmt(f)
+    }
+}
+
+fn is_guid(name: &str) -> bool {
+    name == "guid" || name.ends_with("_guid")
+}
+
+// Arrays of scalars are printed on a single line, with their elements
+// separated by spaces. Booleans are printed as 1 or 0, as done by zdb.
+fn print_value(f: &mut Formatter<'_>, value: &Value) -> Result {
+    match value {
+        Value::BooleanValue(x) => write!(f, "{}", u8::from(*x)),
+        Value::Byte(x) => write!(f, "{}", x),
+        Value::Int8(x) => write!(f, "{}", x),
+        Value::Uint8(x) => write!(f, "{}", x),
+        Value::Int16(x) => write!(f, "{}", x),
+        Value::Uint16(x) => write!(f, "{}", x),
+        Value::Int32(x) => write!(f, "{}", x),
+        Value::Uint32(x) => write!(f, "{}", x),
+        Value::Int64(x) => write!(f, "{}", x),
+        Value::Uint64(x) => write!(f, "{}", x),
+        Value::Hrtime(x) => write!(f, "{:#x}", x),
+        Value::Double(x) => write!(f, "{}", x),
+        Value::String(x) => write!(f, "'{}'", x.to_string_lossy()),
+        Value::ByteArray(xs) => print_array(f, xs, |f, x| write!(f, "{:02x}", x)),
+        Value::BooleanArray(xs) => print_array(f, xs, |f, x| write!(f, "{}", u8::from(*x))),
+        Value::Int8Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Uint8Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Int16Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Uint16Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Int32Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Uint32Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Int64Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::Uint64Array(xs) => print_array(f, xs, |f, x| write!(f, "{}", x)),
+        Value::StringArray(xs) =>
+            print_array(f, xs, |f, x| write!(f, "'{}'", x.to_string_lossy())),
+        Value::Boolean | Value::Nvlist(_) | Value::NvlistArray(_) =>
+            unreachable!("printed by Printer::print()"),
+    }
+}
+
+fn print_array<T>(
+    f: &mut Formatter<'_>,
+    xs: &[T],
+    p: impl Fn(&mut Formatter<'_>, &T) -> Result
+) -> Result {
+    for (i, x) in xs.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        p(f, x)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use binary::Reader;
+
+    fn decode(name: &str) -> crate::Nvlist {
+        let path = format!("{}/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        crate::Nvlist::read(&mut Reader::new(&File::open(path).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn label() {
+        let label = decode("xdr_be.bin").to_string();
+        assert_eq!(label.lines().collect::<Vec<_>>(), [
+            "version: 5000",
+            "name: 'tank'",
+            "txg: 4",
+            "pool_guid: 244837814094590",
+            "vdev_tree:",
+            "    type: 'mirror'",
+            "    children[0]:",
+            "        type: 'file'",
+            "        guid: 29425646",
+            "        path: '/var/tmp/disk0'",
+            "    children[1]:",
+            "features_for_read:",
+            "    com.delphix:hole_birth",
+            "boolean_value: 1",
+            "byte: 171",
+            "int8: -2",
+            "uint8: 200",
+            "int16: -300",
+            "uint16: 60000",
+            "int32: -70000",
+            "uint32: 4000000000",
+            "int64: -5",
+            "hrtime: 0x75bcd15",
+            "double: 0.5",
+            "byte_array: 01 02 03 04 05",
+            "boolean_array: 1 0 1",
+            "int8_array: -1 2 3",
+            "uint8_array: 255",
+            "int16_array: -1 1",
+            "uint16_array: 1 2 3",
+            "int32_array: -1",
+            "uint32_array: ",
+            "int64_array: -1 0 1",
+            "uint64_array: 9223372036854775808",
+            "string_array: 'a' 'bcdef' ''",
+            "\u{fffd}: 1",
+            "\u{fffd}: 'not \u{fffd} utf-8'",
+        ]);
+    }
+
+    #[test]
+    fn hex_guids_and_indent() {
+        let label = decode("native_le.bin");
+        let vdev_tree = label.get_nvlist("vdev_tree").unwrap();
+        let printed = vdev_tree.printer().hex_guids(true).indent(2).to_string();
+        assert_eq!(printed.lines().collect::<Vec<_>>(), [
+            "  type: 'mirror'",
+            "  children[0]:",
+            "      type: 'file'",
+            "      guid: 0x1c0ffee",
+            "      path: '/var/tmp/disk0'",
+            "  children[1]:",
+        ]);
+    }
+}
@@ -0,0 +1,473 @@
+use std::fmt::Display;
+
+use serde::de;
+use serde::ser::{
+    self, Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct,
+    SerializeTuple, SerializeTupleStruct, Serializer
+};
+use xdr::ByteString;
+
+use crate::de::Error;
+use crate::internal::{Nvlist, Value};
+
+/// Nvlists are serialized as maps from nvpair names to their values. Names and
+/// strings that aren't UTF-8 have their invalid sequences replaced.
+impl Serialize for Nvlist {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.pairs().len()))?;
+        for pair in self.iter() {
+            map.serialize_entry(&pair.name().to_string_lossy(), pair.value())?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for crate::Nvlist {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Values are serialized as the closest serde data type. `DATA_TYPE_BOOLEAN`
+/// nvpairs, whose presence is their value, are serialized as `true`.
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Boolean => serializer.serialize_bool(true),
+            Value::BooleanValue(x) => serializer.serialize_bool(*x),
+            Value::Byte(x) => serializer.serialize_u8(*x),
+            Value::Int8(x) => serializer.serialize_i8(*x),
+            Value::Uint8(x) => serializer.serialize_u8(*x),
+            Value::Int16(x) => serializer.serialize_i16(*x),
+            Value::Uint16(x) => serializer.serialize_u16(*x),
+            Value::Int32(x) => serializer.serialize_i32(*x),
+            Value::Uint32(x) => serializer.serialize_u32(*x),
+            Value::Int64(x) => serializer.serialize_i64(*x),
+            Value::Uint64(x) => serializer.serialize_u64(*x),
+            Value::Hrtime(x) => serializer.serialize_i64(*x),
+            Value::Double(x) => serializer.serialize_f64(*x),
+            Value::String(x) => serializer.serialize_str(&x.to_string_lossy()),
+            Value::ByteArray(x) => serializer.serialize_bytes(x),
+            Value::BooleanArray(x) => x.serialize(serializer),
+            Value::Int8Array(x) => x.serialize(serializer),
+            Value::Uint8Array(x) => x.serialize(serializer),
+            Value::Int16Array(x) => x.serialize(serializer),
+            Value::Uint16Array(x) => x.serialize(serializer),
+            Value::Int32Array(x) => x.serialize(serializer),
+            Value::Uint32Array(x) => x.serialize(serializer),
+            Value::Int64Array(x) => x.serialize(serializer),
+            Value::Uint64Array(x) => x.serialize(serializer),
+            Value::StringArray(x) => serializer.collect_seq(x.iter().map(|x| x.to_string_lossy())),
+            Value::Nvlist(x) => x.serialize(serializer),
+            Value::NvlistArray(x) => x.serialize(serializer),
+        }
+    }
+}
+
+/// Serializes a struct or a map into an nvlist, adding an nvpair for each of
+/// its fields in order. Fields set to `None` are left out.
+///
+/// Values are stored as the nvpair data type closest to their serde data type,
+/// e.g. `u32` as `DATA_TYPE_UINT32` and `Vec<String>` as
+/// `DATA_TYPE_STRING_ARRAY`. Sequences must hold elements of a single type
+/// having an array counterpart: empty ones have no type to go by and are
+/// stored as empty nvlist arrays.
+pub fn to_nvlist<T: Serialize + ?Sized>(value: &T) -> Result<Nvlist, Error> {
+    match value.serialize(ValueSerializer)? {
+        Some(Value::Nvlist(x)) => Ok(x),
+        _ => Err(ser::Error::custom("only structs and maps serialize into nvlists")),
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        de::Error::custom(msg)
+    }
+}
+
+/// Serializes a value into the value of an nvpair, or into nothing for `None`.
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
+    type SerializeMap = NvlistSerializer;
+    type SerializeStruct = NvlistSerializer;
+    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+
+    fn serialize_bool(self, x: bool) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::BooleanValue(x)))
+    }
+
+    fn serialize_i8(self, x: i8) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Int8(x)))
+    }
+
+    fn serialize_i16(self, x: i16) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Int16(x)))
+    }
+
+    fn serialize_i32(self, x: i32) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Int32(x)))
+    }
+
+    fn serialize_i64(self, x: i64) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Int64(x)))
+    }
+
+    fn serialize_u8(self, x: u8) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Uint8(x)))
+    }
+
+    fn serialize_u16(self, x: u16) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Uint16(x)))
+    }
+
+    fn serialize_u32(self, x: u32) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Uint32(x)))
+    }
+
+    fn serialize_u64(self, x: u64) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Uint64(x)))
+    }
+
+    fn serialize_f32(self, x: f32) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Double(x.into())))
+    }
+
+    fn serialize_f64(self, x: f64) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Double(x)))
+    }
+
+    fn serialize_char(self, x: char) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::String(x.to_string().into())))
+    }
+
+    fn serialize_str(self, x: &str) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::String(x.into())))
+    }
+
+    fn serialize_bytes(self, x: &[u8]) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::ByteArray(x.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, x: &T) -> Result<Self::Ok, Error> {
+        x.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Err(unsupported("()"))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Error> {
+        Err(unsupported(name))
+    }
+
+    // Unit variants are strings, as they are deserialized.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str
+    ) -> Result<Self::Ok, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        x: &T
+    ) -> Result<Self::Ok, Error> {
+        x.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _x: &T
+    ) -> Result<Self::Ok, Error> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<NvlistSerializer, Error> {
+        Ok(NvlistSerializer { nvlist: Nvlist::default(), name: None })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize
+    ) -> Result<NvlistSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported(name))
+    }
+}
+
+struct SeqSerializer(Vec<Value>);
+
+impl SerializeSeq for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, x: &T) -> Result<(), Error> {
+        let x = x.serialize(ValueSerializer)?
+            .ok_or_else(|| ser::Error::custom("arrays can't hold missing values"))?;
+        self.0.push(x);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        array(self.0).map(Some)
+    }
+}
+
+impl SerializeTuple for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, x: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, x)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, x: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, x)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+struct NvlistSerializer {
+    nvlist: Nvlist,
+    // Name of the nvpair whose value is to be serialized next.
+    name: Option<ByteString>,
+}
+
+impl SerializeMap for NvlistSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Some(Value::String(name)) => self.name = Some(name),
+            _ => return Err(ser::Error::custom("nvpair names must be strings")),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, x: &T) -> Result<(), Error> {
+        let name = self.name.take()
+            .ok_or_else(|| ser::Error::custom("value serialized before its name"))?;
+        if let Some(x) = x.serialize(ValueSerializer)? {
+            self.nvlist.add(name, x);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(Value::Nvlist(self.nvlist)))
+    }
+}
+
+impl SerializeStruct for NvlistSerializer {
+    type Ok = Option<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        x: &T
+    ) -> Result<(), Error> {
+        if let Some(x) = x.serialize(ValueSerializer)? {
+            self.nvlist.add(name, x);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Generates the conversion of a sequence of values into the array holding
+/// their data type.
+macro_rules! array {
+    ($xs:expr, $($variant:ident => $array:ident,)*) => {
+        match $xs.first() {
+            None => Ok(Value::NvlistArray(Vec::new())),
+            $(
+                Some(Value::$variant(_)) => $xs.into_iter()
+                    .map(|x| match x {
+                        Value::$variant(x) => Ok(x),
+                        _ => Err(ser::Error::custom("array elements must have the same type")),
+                    })
+                    .collect::<Result<_, Error>>()
+                    .map(Value::$array),
+            )*
+            Some(x) => Err(ser::Error::custom(
+                format!("no array can hold {:?} elements", x.data_type())
+            )),
+        }
+    };
+}
+
+fn array(xs: Vec<Value>) -> Result<Value, Error> {
+    array! {
+        xs,
+        BooleanValue => BooleanArray,
+        Int8 => Int8Array,
+        Uint8 => Uint8Array,
+        Int16 => Int16Array,
+        Uint16 => Uint16Array,
+        Int32 => Int32Array,
+        Uint32 => Uint32Array,
+        Int64 => Int64Array,
+        Uint64 => Uint64Array,
+        String => StringArray,
+        Nvlist => NvlistArray,
+    }
+}
+
+fn unsupported(name: &str) -> Error {
+    ser::Error::custom(format!("{} can't be serialized into an nvpair", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{from_nvlist, Builder};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum VdevType {
+        Mirror,
+        File,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct VdevTree {
+        r#type: VdevType,
+        guid: u64,
+        path: Option<String>,
+        #[serde(default)]
+        children: Vec<VdevTree>,
+    }
+
+    fn vdev_tree() -> VdevTree {
+        VdevTree {
+            r#type: VdevType::Mirror,
+            guid: 1,
+            path: None,
+            children: vec![VdevTree {
+                r#type: VdevType::File,
+                guid: 2,
+                path: Some("/var/tmp/disk0".into()),
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn struct_into_nvlist() {
+        let disk = Builder::new()
+            .add("type", "file")
+            .add("guid", 2u64)
+            .add("path", "/var/tmp/disk0")
+            .add("children", Vec::<Nvlist>::new())
+            .build();
+        let expected = Builder::new()
+            .add("type", "mirror")
+            .add("guid", 1u64)
+            .add("children", vec![disk])
+            .build();
+        assert_eq!(to_nvlist(&vdev_tree()).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let nvlist = to_nvlist(&vdev_tree()).unwrap();
+        assert_eq!(from_nvlist::<VdevTree>(&nvlist).unwrap(), vdev_tree());
+    }
+
+    #[test]
+    fn arrays() {
+        #[derive(Serialize)]
+        struct Arrays {
+            guids: Vec<u64>,
+            paths: [&'static str; 2],
+        }
+        let nvlist = to_nvlist(&Arrays { guids: vec![1, 2], paths: ["a", "b"] }).unwrap();
+        assert_eq!(nvlist.get_u64_array("guids").unwrap(), [1, 2]);
+        assert_eq!(nvlist.get_str_array("paths").unwrap(), ["a", "b"]);
+        let bytes = ValueSerializer.serialize_bytes(&[0xff]).unwrap();
+        assert_eq!(bytes, Some(Value::ByteArray(vec![0xff])));
+    }
+
+    #[test]
+    fn unsupported() {
+        #[derive(Serialize)]
+        struct Mixed {
+            xs: (u64, String),
+        }
+        assert!(to_nvlist(&Mixed { xs: (1, "a".into()) }).is_err());
+        assert!(to_nvlist(&1u64).is_err());
+        assert!(to_nvlist(&vec![vdev_tree()]).is_err());
+    }
+}
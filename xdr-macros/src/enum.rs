@@ -4,7 +4,7 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{Ident, ItemEnum, TypePath, parse2};
 
-use enum_macros_common::int_enum::common::get_variants;
+use enum_macros_common::int_enum::common::{Variant, Variants, get_variants};
 use enum_macros_common::int_enum::impl_try_from_type;
 
 // This is already defined in ::xdr::primitive, but redefining it here allows us
@@ -15,7 +15,7 @@ pub fn derive_enum(e: &ItemEnum) -> TokenStream {
     match get_variants(e) {
         Ok(vs) => {
             let impl_try_from = impl_try_from_type::r#impl(&e.ident, &vs, &i32());
-            let r#impl = r#impl(&e.ident);
+            let r#impl = r#impl(&e.ident, &vs);
             quote! { #impl_try_from #r#impl }
         },
         Err(err) => err.to_compile_error()
@@ -29,30 +29,27 @@ fn i32() -> TypePath {
     ).unwrap()
 }
 
-fn r#impl(enum_name: &Ident) -> TokenStream {
+fn r#impl(enum_name: &Ident, variants: &Variants) -> TokenStream {
     let decode = decode();
-    let read = read();
-    quote!{ impl #enum_name { #read #decode } }
+    let read = read(enum_name);
+    let write = write();
+    let encode = encode(variants);
+    quote!{ impl #enum_name { #read #decode #write #encode } }
 }
 
-fn read() -> TokenStream {
+fn read(enum_name: &Ident) -> TokenStream {
+    let name = enum_name.to_string();
     quote!{
         pub fn read(
             r: &mut ::binary::Reader
-        ) -> ::std::io::Result<Self> {
-            r.try_read_as::<Self, ::std::io::Error, #I32_SIZE>(
+        ) -> ::binary::Result<Self> {
+            let v = r.try_read_as::<Self, ::binary::Error, #I32_SIZE>(
                 |x|
-                match Self::decode(x) {
-                    ::core::result::Result::Ok(v) => ::std::io::Result::Ok(v),
-                    // TODO: Implement better error reporting.
-                    ::core::result::Result::Err(n) => ::std::io::Result::Err(
-                        ::std::io::Error::new(
-                            ::std::io::ErrorKind::InvalidInput,
-                            n.to_string()
-                        )
-                    )
-                }
-            )
+                Self::decode(x).map_err(|n| ::binary::Error::invalid(
+                    ::std::format!("invalid discriminant {}", n)
+                ))
+            );
+            ::binary::Context::decoding(v, #name)
         }
     }
 }
@@ -67,3 +64,26 @@ fn decode() -> TokenStream {
         }
     }
 }
+
+fn write() -> TokenStream {
+    quote!{
+        pub fn write(
+            &self,
+            w: &mut ::binary::Writer
+        ) -> ::binary::Result<()> {
+            w.write_as(self, Self::encode)
+        }
+    }
+}
+
+fn encode(variants: &Variants) -> TokenStream {
+    let cases = variants.iter().map(
+        |Variant { ident, expr }| quote! { Self::#ident => #expr }
+    );
+    quote!{
+        pub fn encode(&self) -> [::core::primitive::u8; #I32_SIZE] {
+            let v: ::core::primitive::i32 = match self { #(#cases),* };
+            ::xdr::I32::from(v).encode()
+        }
+    }
+}
@@ -1,9 +1,11 @@
 //! XDR: External Data Representation Standard (IETF RFC 4506)
 
 mod r#enum;
+mod r#struct;
+mod union;
 
 use proc_macro::TokenStream;
-use syn::{ItemEnum, parse_macro_input};
+use syn::{DeriveInput, ItemEnum, parse_macro_input};
 
 /// Enumeration
 ///
@@ -11,12 +13,16 @@ use syn::{ItemEnum, parse_macro_input};
 /// Enumerations are handy for describing subsets of the integers.
 /// Enumerated data is declared as follows:
 ///
-///     enum { name-identifier = constant, ... } identifier;
+/// ```text
+/// enum { name-identifier = constant, ... } identifier;
+/// ```
 ///
 /// For example, the three colors red, yellow, and blue could be
 /// described by an enumerated type:
 ///
-///     enum { RED = 2, YELLOW = 3, BLUE = 5 } colors;
+/// ```text
+/// enum { RED = 2, YELLOW = 3, BLUE = 5 } colors;
+/// ```
 ///
 /// It is an error to encode as an enum any other integer than those that
 /// have been given assignments in the enum declaration.
@@ -25,3 +31,82 @@ pub fn derive_enum(input: TokenStream) -> TokenStream {
     let e = parse_macro_input!(input as ItemEnum);
     r#enum::derive_enum(&e).into()
 }
+
+/// Structure
+///
+/// Structures are declared as follows:
+///
+/// ```text
+/// struct {
+///    component-declaration-A;
+///    component-declaration-B;
+///    ...
+/// } identifier;
+/// ```
+///
+/// The components of the structure are encoded in the order of their
+/// declaration in the structure.  Each component's size is a multiple
+/// of four bytes, though the components may be different sizes.
+///
+/// ```text
+/// +-------------+-------------+...
+/// | component A | component B |...                      STRUCTURE
+/// +-------------+-------------+...
+/// ```
+///
+/// Fields must be XDR types themselves. Variable-length fields declared as
+/// `identifier<m>` are annotated with `#[xdr(max = m)]`.
+#[proc_macro_derive(Struct, attributes(xdr))]
+pub fn derive_struct(input: TokenStream) -> TokenStream {
+    let s = parse_macro_input!(input as DeriveInput);
+    r#struct::derive_struct(&s).into()
+}
+
+/// Discriminated Union
+///
+/// A discriminated union is a type composed of a discriminant followed
+/// by a type selected from a set of prearranged types according to the
+/// value of the discriminant.  The type of discriminant is either "int",
+/// "unsigned int", or an enumerated type, such as "bool".  The component
+/// types are called "arms" of the union and are preceded by the value of
+/// the discriminant that implies their encoding.  Discriminated unions
+/// are declared as follows:
+///
+/// ```text
+/// union switch (discriminant-declaration) {
+///  case discriminant-value-A:
+///     arm-declaration-A;
+///  case discriminant-value-B:
+///     arm-declaration-B;
+///  ...
+///  default: default-declaration;
+/// } identifier;
+/// ```
+///
+/// Each "case" keyword is followed by a legal value of the discriminant.
+/// The default arm is optional.  If it is not specified, then a valid
+/// encoding of the union cannot take on unspecified discriminant values.
+/// The size of the implied arm is always a multiple of four bytes.
+///
+/// The discriminated union is encoded as its discriminant followed by
+/// the encoding of the implied arm.
+///
+/// ```text
+///   0   1   2   3
+/// +---+---+---+---+---+---+---+---+
+/// |  discriminant |  implied arm  |          DISCRIMINATED UNION
+/// +---+---+---+---+---+---+---+---+
+/// |<---4 bytes--->|
+/// ```
+///
+/// Arms are the variants of an enum with `#[repr(i32)]` or `#[repr(u32)]`,
+/// which declares the type of the discriminant. Each variant is given its
+/// case as an explicit discriminant, which can be the value of an XDR enum
+/// such as `Kind::Foo as i32`. Void arms are unit variants. The default arm
+/// is marked with `#[xdr(default)]`: its first field holds the discriminant
+/// and its own discriminant is ignored.
+#[proc_macro_derive(Union, attributes(xdr))]
+pub fn derive_union(input: TokenStream) -> TokenStream {
+    let u = parse_macro_input!(input as DeriveInput);
+    union::derive_union(&u).into()
+}
@@ -0,0 +1,171 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Error, Expr, Field, Fields, GenericArgument, Ident,
+    PathArguments, Result, Type
+};
+
+pub fn derive_struct(s: &DeriveInput) -> TokenStream {
+    match r#impl(s) {
+        Ok(ts) => ts,
+        Err(err) => err.to_compile_error()
+    }
+}
+
+fn r#impl(s: &DeriveInput) -> Result<TokenStream> {
+    let fields = match &s.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(
+                &s.ident,
+                "only structs with named fields can derive Struct"
+            ))
+        },
+        _ => return Err(Error::new_spanned(&s.ident, "Struct can only be derived by structs"))
+    };
+
+    let reads = fields.iter().map(read_field).collect::<Result<Vec<_>>>()?;
+    let writes = fields.iter().map(write_field).collect::<Result<Vec<_>>>()?;
+    let names = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let name = &s.ident;
+    let (impl_generics, type_generics, where_clause) = s.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            pub fn read(
+                r: &mut ::binary::Reader
+            ) -> ::binary::Result<Self> {
+                #(#reads)*
+                ::binary::Result::Ok(Self { #(#names),* })
+            }
+
+            pub fn write(
+                &self,
+                w: &mut ::binary::Writer
+            ) -> ::binary::Result<()> {
+                let Self { #(#names),* } = self;
+                #(#writes)*
+                ::binary::Result::Ok(())
+            }
+        }
+    })
+}
+
+// Components are read in the order of their declaration, adding their name to
+// the path of the errors.
+pub(crate) fn read_field(field: &Field) -> Result<TokenStream> {
+    // Only named fields get here.
+    let ident = field.ident.as_ref().unwrap();
+    let name = ident.to_string();
+    let read = read(field)?;
+    Ok(quote! {
+        let #ident = ::binary::Context::field(#read, #name)?;
+    })
+}
+
+/// Expression reading the value of a field from `r`.
+pub(crate) fn read(field: &Field) -> Result<TokenStream> {
+    Ok(match max(field)? {
+        Some(max) => read_max(&field.ty, &max),
+        None => {
+            let reader = reader(&field.ty);
+            quote! { (#reader)(r) }
+        }
+    })
+}
+
+/// Writes the value of a named field, bound to a variable of the same name.
+pub(crate) fn write_field(field: &Field) -> Result<TokenStream> {
+    // Only named fields get here.
+    let ident = field.ident.as_ref().unwrap();
+    let name = ident.to_string();
+    let write = write(field, ident)?;
+    Ok(quote! {
+        ::binary::Context::field(#write, #name)?;
+    })
+}
+
+/// Expression writing the value of a field, bound to the given variable, to
+/// `w`.
+pub(crate) fn write(field: &Field, value: &Ident) -> Result<TokenStream> {
+    Ok(match max(field)? {
+        Some(max) => write_max(&field.ty, &max, value),
+        None => {
+            let writer = writer(&field.ty);
+            quote! { (#writer)(#value, w) }
+        }
+    })
+}
+
+// Variable-length data declared as `identifier<m>`.
+fn read_max(ty: &Type, max: &Expr) -> TokenStream {
+    match array_element(ty) {
+        Some(element) => {
+            let reader = reader(element);
+            quote! { ::xdr::Array::read_max(r, #max, #reader) }
+        },
+        None => quote! { <#ty>::read_max(r, #max) }
+    }
+}
+
+// Expression of a function reading a value of the given type. Arrays need to
+// be told how to read their elements.
+fn reader(ty: &Type) -> TokenStream {
+    match array_element(ty) {
+        Some(element) => {
+            let reader = reader(element);
+            quote! { |r: &mut ::binary::Reader| ::xdr::Array::read(r, #reader) }
+        },
+        None => quote! { <#ty>::read }
+    }
+}
+
+fn write_max(ty: &Type, max: &Expr, value: &Ident) -> TokenStream {
+    match array_element(ty) {
+        Some(element) => {
+            let writer = writer(element);
+            quote! { #value.write_max(w, #max, #writer) }
+        },
+        None => quote! { #value.write_max(w, #max) }
+    }
+}
+
+fn writer(ty: &Type) -> TokenStream {
+    match array_element(ty) {
+        Some(element) => {
+            let writer = writer(element);
+            quote! {
+                |x: &#ty, w: &mut ::binary::Writer| x.write(w, #writer)
+            }
+        },
+        None => quote! { <#ty>::write }
+    }
+}
+
+fn array_element(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Array" {
+        return None
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else { return None };
+    match args.args.first()? {
+        GenericArgument::Type(t) if args.args.len() == 1 => Some(t),
+        _ => None
+    }
+}
+
+// Parses `#[xdr(max = N)]`.
+fn max(field: &Field) -> Result<Option<Expr>> {
+    let mut max = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("xdr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("max") {
+                max = Some(meta.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported xdr attribute"))
+            }
+        })?;
+    }
+    Ok(max)
+}
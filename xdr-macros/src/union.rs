@@ -0,0 +1,227 @@
+use proc_macro2::TokenStream;
+use quote::{ToTokens, format_ident, quote};
+use syn::{
+    Attribute, Data, DataEnum, DeriveInput, Error, Fields, Ident, ItemEnum,
+    Result, Variant, Visibility
+};
+
+use enum_macros_common::int_enum::common::get_variants;
+
+use crate::r#struct::{read, read_field, write, write_field};
+
+pub fn derive_union(u: &DeriveInput) -> TokenStream {
+    match r#impl(u) {
+        Ok(ts) => ts,
+        Err(err) => err.to_compile_error()
+    }
+}
+
+fn r#impl(u: &DeriveInput) -> Result<TokenStream> {
+    let Data::Enum(data) = &u.data else {
+        return Err(Error::new_spanned(&u.ident, "Union can only be derived by enums"))
+    };
+    let (discriminant, repr) = discriminant(&u.ident, &u.attrs)?;
+
+    let mut default = None;
+    let mut arms = Vec::with_capacity(data.variants.len());
+    for variant in data.variants.iter() {
+        if is_default(variant)? {
+            if default.replace(variant).is_some() {
+                return Err(Error::new_spanned(variant, "only one arm can be the default"))
+            }
+        } else {
+            arms.push(variant.clone());
+        }
+    }
+
+    // The variants of the other arms must all have a discriminant, used as the
+    // value of their case.
+    let cases = ItemEnum {
+        attrs: Vec::new(),
+        vis: Visibility::Inherited,
+        enum_token: data.enum_token,
+        ident: u.ident.clone(),
+        generics: u.generics.clone(),
+        brace_token: data.brace_token,
+        variants: arms.into_iter().collect(),
+    };
+    let variants = get_variants(&cases)?;
+    let cases = variants.iter()
+        .map(|v| {
+            let variant = variant(data, v.ident);
+            let expr = v.expr;
+            let read = read_arm(variant, &[])?;
+            Ok(quote! { d if d == (#expr) => { #read } })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut writes = variants.iter()
+        .map(|v| {
+            let case = v.expr.to_token_stream();
+            write_arm(variant(data, v.ident), &case, 0, &discriminant, &repr)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(variant) = default {
+        writes.push(write_arm(variant, &quote! { *d }, 1, &discriminant, &repr)?);
+    }
+    let default = match default {
+        Some(variant) => read_default(variant)?,
+        None => {
+            let name = u.ident.to_string();
+            quote! {
+                d => ::binary::Result::Err(::binary::Error::invalid(
+                    ::std::format!("invalid discriminant {}", d)
+                ).at(position).decoding(#name))
+            }
+        }
+    };
+
+    let name = &u.ident;
+    let (impl_generics, type_generics, where_clause) = u.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #type_generics #where_clause {
+            pub fn read(
+                r: &mut ::binary::Reader
+            ) -> ::binary::Result<Self> {
+                let position = r.position()?;
+                let d: #repr = #discriminant::read(r)?.into();
+                match d {
+                    #(#cases)*
+                    #default
+                }
+            }
+
+            pub fn write(
+                &self,
+                w: &mut ::binary::Writer
+            ) -> ::binary::Result<()> {
+                match self {
+                    #(#writes)*
+                }
+                ::binary::Result::Ok(())
+            }
+        }
+    })
+}
+
+// The discriminant is encoded as a signed or unsigned integer, as declared
+// by the representation of the enum.
+fn discriminant(name: &Ident, attrs: &[Attribute]) -> Result<(TokenStream, Ident)> {
+    let mut repr = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("i32") || meta.path.is_ident("u32") {
+                repr = meta.path.get_ident().cloned();
+            }
+            Ok(())
+        })?;
+    }
+    match repr {
+        Some(t) if t == "i32" => Ok((quote! { ::xdr::I32 }, t)),
+        Some(t) => Ok((quote! { ::xdr::U32 }, t)),
+        None => Err(Error::new_spanned(
+            name,
+            "Union requires #[repr(i32)] or #[repr(u32)] to define the discriminant"
+        ))
+    }
+}
+
+fn variant<'a>(data: &'a DataEnum, ident: &Ident) -> &'a Variant {
+    // get_variants() only returns variants of the enum.
+    data.variants.iter().find(|v| v.ident == *ident).unwrap()
+}
+
+// Parses `#[xdr(default)]`.
+fn is_default(variant: &Variant) -> Result<bool> {
+    let mut default = false;
+    for attr in variant.attrs.iter().filter(|a| a.path().is_ident("xdr")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("default") {
+                default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported xdr attribute"))
+            }
+        })?;
+    }
+    Ok(default)
+}
+
+// The first field of the default arm receives the discriminant, the others
+// are read like the fields of any other arm.
+fn read_default(variant: &Variant) -> Result<TokenStream> {
+    match &variant.fields {
+        Fields::Unnamed(fields) if !fields.unnamed.is_empty() => {
+            let read = read_arm(variant, &[quote! { d }])?;
+            Ok(quote! { d => { #read } })
+        },
+        _ => Err(Error::new_spanned(
+            variant,
+            "the default arm must be a tuple variant whose first field holds the discriminant"
+        ))
+    }
+}
+
+// Reads the fields of an arm and builds the variant, after the given leading
+// values.
+fn read_arm(variant: &Variant, leading: &[TokenStream]) -> Result<TokenStream> {
+    let ident = &variant.ident;
+    match &variant.fields {
+        Fields::Unit => Ok(quote! { ::binary::Result::Ok(Self::#ident) }),
+        Fields::Named(fields) => {
+            let reads = fields.named.iter().map(read_field).collect::<Result<Vec<_>>>()?;
+            let names = fields.named.iter().map(|f| &f.ident);
+            Ok(quote! {
+                #(#reads)*
+                ::binary::Result::Ok(Self::#ident { #(#names),* })
+            })
+        },
+        Fields::Unnamed(fields) => {
+            let fields = fields.unnamed.iter().skip(leading.len());
+            let names = (0..fields.len()).map(|i| format_ident!("x{}", i)).collect::<Vec<_>>();
+            let reads = fields.map(read).collect::<Result<Vec<_>>>()?;
+            Ok(quote! {
+                #(let #names = #reads?;)*
+                ::binary::Result::Ok(Self::#ident(#(#leading,)* #(#names),*))
+            })
+        }
+    }
+}
+
+// Writes the discriminant given by case, then the fields of the arm after the
+// leading ones.
+fn write_arm(
+    variant: &Variant,
+    case: &TokenStream,
+    leading: usize,
+    discriminant: &TokenStream,
+    repr: &Ident
+) -> Result<TokenStream> {
+    let ident = &variant.ident;
+    let discriminant = quote! {
+        let d: #repr = #case;
+        #discriminant::from(d).write(w)?;
+    };
+    match &variant.fields {
+        Fields::Unit => Ok(quote! {
+            Self::#ident => { #discriminant }
+        }),
+        Fields::Named(fields) => {
+            let writes = fields.named.iter().map(write_field).collect::<Result<Vec<_>>>()?;
+            let names = fields.named.iter().map(|f| &f.ident);
+            Ok(quote! {
+                Self::#ident { #(#names),* } => { #discriminant #(#writes)* }
+            })
+        },
+        Fields::Unnamed(fields) => {
+            let names = (0..fields.unnamed.len()).map(|i| format_ident!("x{}", i)).collect::<Vec<_>>();
+            let writes = fields.unnamed.iter().zip(names.iter()).skip(leading)
+                .map(|(f, name)| write(f, name))
+                .collect::<Result<Vec<_>>>()?;
+            let d = (0..leading).map(|_| quote! { d });
+            let names = names.iter().skip(leading);
+            Ok(quote! {
+                Self::#ident(#(#d,)* #(#names),*) => { #discriminant #(#writes?;)* }
+            })
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use binary::{Context, Reader, Result, Writer};
+
+use crate::opaque::{read_len, write_len};
+
+/// Variable-Length Array
+///
+/// Counted arrays provide the ability to encode variable-length arrays
+/// of homogeneous elements.  The array is encoded as the element count n
+/// (an unsigned integer) followed by the encoding of each of the array's
+/// elements, starting with element 0 and progressing through element
+/// n-1.  The declaration for variable-length arrays follows this form:
+///
+/// ```text
+/// type-name identifier<m>;
+/// ```
+///
+/// or
+///
+/// ```text
+/// type-name identifier<>;
+/// ```
+///
+/// The constant m specifies the maximum acceptable element count of an
+/// array; if m is not specified, as in the second declaration, it is
+/// assumed to be (2**32) - 1.
+///
+/// ```text
+///   0  1  2  3
+/// +--+--+--+--+--+--+--+--+--+--+--+--+...+--+--+--+--+
+/// |     n     | element 0 | element 1 |...|element n-1|
+/// +--+--+--+--+--+--+--+--+--+--+--+--+...+--+--+--+--+
+/// |<-4 bytes->|<--------------n elements------------->|
+///                                                 COUNTED ARRAY
+/// ```
+///
+/// It is an error to encode a value of n that is greater than the
+/// maximum described in the specification.
+///
+/// Fixed-Length Array
+///
+/// Declarations for fixed-length arrays of homogeneous elements are in
+/// the following form:
+///
+/// ```text
+/// type-name identifier[n];
+/// ```
+///
+/// Fixed-length arrays of elements numbered 0 through n-1 are encoded by
+/// individually encoding the elements of the array in their natural
+/// order, 0 through n-1.  Each element's size is a multiple of four
+/// bytes.  Though all elements are of the same type, the elements may
+/// have different sizes.  For example, in a fixed-length array of
+/// strings, all elements are of type "string", yet each element will
+/// vary in its length.
+///
+/// ```text
+/// +---+---+---+---+---+---+---+---+...+---+---+---+---+
+/// |   element 0   |   element 1   |...|  element n-1  |
+/// +---+---+---+---+---+---+---+---+...+---+---+---+---+
+/// |<--------------------n elements------------------->|
+///
+///                                           FIXED-LENGTH ARRAY
+/// ```
+///
+/// Elements are read and written with the given functions, e.g.
+/// `xdr::U64::read` and `xdr::U64::write`.
+#[derive(Debug)]
+pub struct Array<T>(Vec<T>);
+
+impl<T> Array<T> {
+    /// Reads `type-name identifier<>`.
+    pub fn read(
+        r: &mut Reader,
+        f: impl FnMut(&mut Reader) -> Result<T>
+    ) -> Result<Self> {
+        Self::read_max(r, u32::MAX, f)
+    }
+
+    /// Reads `type-name identifier<m>`, failing if more than max elements
+    /// follow.
+    pub fn read_max(
+        r: &mut Reader,
+        max: u32,
+        f: impl FnMut(&mut Reader) -> Result<T>
+    ) -> Result<Self> {
+        let len = read_len(r, max).decoding("xdr::Array")?;
+        Self::read_fixed(r, len, f)
+    }
+
+    /// Reads `type-name identifier[n]`.
+    pub fn read_fixed(
+        r: &mut Reader,
+        len: usize,
+        mut f: impl FnMut(&mut Reader) -> Result<T>
+    ) -> Result<Self> {
+        // Collecting allocates all of the elements upfront.
+        r.check_alloc(len.saturating_mul(std::mem::size_of::<T>()))?;
+        let xs = (0..len).map(|i| f(r).element(i)).collect::<Result<_>>()?;
+        Ok(Array(xs))
+    }
+
+    /// Writes `type-name identifier<>`.
+    pub fn write(
+        &self,
+        w: &mut Writer,
+        f: impl FnMut(&T, &mut Writer) -> Result<()>
+    ) -> Result<()> {
+        self.write_max(w, u32::MAX, f)
+    }
+
+    /// Writes `type-name identifier<m>`, failing if there are more than max
+    /// elements.
+    pub fn write_max(
+        &self,
+        w: &mut Writer,
+        max: u32,
+        f: impl FnMut(&T, &mut Writer) -> Result<()>
+    ) -> Result<()> {
+        write_len(w, self.0.len(), max)?;
+        self.write_fixed(w, f)
+    }
+
+    /// Writes `type-name identifier[n]`.
+    pub fn write_fixed(
+        &self,
+        w: &mut Writer,
+        mut f: impl FnMut(&T, &mut Writer) -> Result<()>
+    ) -> Result<()> {
+        self.0.iter().enumerate().try_for_each(|(i, x)| f(x, w).element(i))
+    }
+}
+
+impl<T> From<Array<T>> for Vec<T> {
+    fn from(x: Array<T>) -> Vec<T> { x.0 }
+}
+
+impl<T> From<Vec<T>> for Array<T> {
+    fn from(x: Vec<T>) -> Array<T> { Array(x) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::{I32, U64};
+
+    fn read_i32(r: &mut Reader) -> Result<i32> {
+        Ok(I32::read(r)?.into())
+    }
+
+    #[test]
+    fn variable_length() -> Result<()> {
+        let mut r = Reader::from_bytes(&[0, 0, 0, 2, 0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Vec::from(Array::read(&mut r, read_i32)?), [1, -1]);
+        assert_eq!(r.position()?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_length() -> Result<()> {
+        let mut r = Reader::from_bytes(&[0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(Vec::from(Array::read_fixed(&mut r, 2, read_i32)?), [2, 1]);
+        assert!(Vec::from(Array::read_fixed(&mut r, 0, read_i32)?).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn over_max() {
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2];
+        let err = Array::read_max(&mut Reader::from_bytes(&bytes), 1, read_i32).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "length 2 exceeds the maximum of 1 while decoding xdr::Array at offset 0x0"
+        );
+        assert!(Array::read_max(&mut Reader::from_bytes(&bytes), 2, read_i32).is_ok());
+    }
+
+    #[test]
+    fn hostile_length() {
+        let bytes = [0x7f, 0xff, 0xff, 0xff, 0, 0, 0, 1];
+        let err = Array::read(&mut Reader::from_bytes(&bytes), read_i32).unwrap_err();
+        assert!(err.to_string().starts_with("can't allocate"));
+
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2];
+        let mut r = Reader::from_bytes(&bytes).with_max_alloc(8);
+        let err = Array::read(&mut r, |r| Ok(u64::from(U64::read(r)?))).unwrap_err();
+        assert!(err.to_string().starts_with("can't allocate 16 bytes, the limit is 8"));
+        let mut r = Reader::from_bytes(&bytes).with_max_alloc(16);
+        assert!(Array::read(&mut r, |r| Ok(u64::from(U64::read(r)?))).is_ok());
+    }
+
+    #[test]
+    fn write() -> Result<()> {
+        let xs = Array::from(vec![1, -1]);
+        let f = |x: &i32, w: &mut Writer| I32::from(*x).write(w);
+        assert_eq!(
+            Writer::to_vec(|w| xs.write(w, f))?,
+            [0, 0, 0, 2, 0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff]
+        );
+        assert_eq!(Writer::to_vec(|w| xs.write_fixed(w, f))?, [0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff]);
+        let err = Writer::to_vec(|w| xs.write_max(w, 1, f)).unwrap_err();
+        assert_eq!(err.to_string(), "length 2 exceeds the maximum of 1 at offset 0x0");
+        Ok(())
+    }
+
+    #[test]
+    fn bad_element() {
+        let bytes = [0, 0, 0, 2, 0, 0, 0, 1, 0, 0];
+        let err = Array::read(&mut Reader::from_bytes(&bytes), read_i32).unwrap_err();
+        assert_eq!(err.path().collect::<Vec<_>>(), [&binary::Segment::Index(1)]);
+    }
+}
@@ -1,9 +1,15 @@
 //! XDR: External Data Representation Standard (IETF RFC 4506)
 
+mod array;
+mod opaque;
 mod primitive;
 mod string;
 
-pub use xdr_macros::Enum;
+pub use xdr_macros::{Enum, Struct, Union};
 
-pub use crate::primitive::{I32, U32, I64, U64};
-pub use crate::string::String;
+pub use crate::array::Array;
+pub use crate::opaque::Opaque;
+pub use crate::primitive::{
+    Bool, Double, Float, I32, I64, Quadruple, U32, U64, Void
+};
+pub use crate::string::{ByteString, String};
@@ -0,0 +1,235 @@
+use binary::{Context, Error, Reader, Result, Writer};
+
+use crate::primitive::U32;
+
+/// Variable-Length Opaque Data
+///
+/// The standard also provides for variable-length (counted) opaque data,
+/// defined as a sequence of n (numbered 0 through n-1) arbitrary bytes
+/// to be the number n encoded as an unsigned integer (as described
+/// below), and followed by the n bytes of the sequence.
+///
+/// Byte m of the sequence always precedes byte m+1 of the sequence, and
+/// byte 0 of the sequence always follows the sequence's length (count).
+/// If n is not a multiple of four, then the n bytes are followed by
+/// enough (0 to 3) residual zero bytes, r, to make the total byte count
+/// a multiple of four.  Variable-length opaque data is declared in the
+/// following way:
+///
+/// ```text
+/// opaque identifier<m>;
+/// ```
+///
+/// or
+///
+/// ```text
+/// opaque identifier<>;
+/// ```
+///
+/// The constant m denotes an upper bound of the number of bytes that the
+/// sequence may contain.  If m is not specified, as in the second
+/// declaration, it is assumed to be (2**32) - 1, the maximum length.
+///
+/// The constant m would normally be found in a protocol specification.
+/// For example, a filing protocol may state that the maximum data
+/// transfer size is 8192 bytes, as follows:
+///
+/// ```text
+/// opaque filedata<8192>;
+/// ```
+///
+/// This can be illustrated as follows:
+///
+/// ```text
+///    0     1     2     3     4     5   ...
+/// +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
+/// |        length n       |byte0|byte1|...| n-1 |  0  |...|  0  |
+/// +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
+/// |<-------4 bytes------->|<------n bytes------>|<---r bytes--->|
+///                         |<----n+r (where (n+r) mod 4 = 0)---->|
+///                                          VARIABLE-LENGTH OPAQUE
+/// ```
+///
+/// It is an error to encode a length greater than the maximum described
+/// in the specification.
+///
+/// Fixed-Length Opaque Data
+///
+/// At times, fixed-length uninterpreted data needs to be passed among
+/// machines.  This data is called "opaque" and is declared as follows:
+///
+/// ```text
+/// opaque identifier[n];
+/// ```
+///
+/// where the constant n is the (static) number of bytes necessary to
+/// contain the opaque data.  If n is not a multiple of four, then the n
+/// bytes are followed by enough (0 to 3) residual zero bytes, r, to make
+/// the total byte count of the opaque object a multiple of four.
+///
+/// ```text
+///     0        1     ...
+/// +--------+--------+...+--------+--------+...+--------+
+/// | byte 0 | byte 1 |...|byte n-1|    0   |...|    0   |
+/// +--------+--------+...+--------+--------+...+--------+
+/// |<-----------n bytes---------->|<------r bytes------>|
+/// |<-----------n+r (where (n+r) mod 4 = 0)------------>|
+///                                              FIXED-LENGTH OPAQUE
+/// ```
+#[derive(Debug)]
+pub struct Opaque(Vec<u8>);
+
+impl Opaque {
+    /// Reads `opaque identifier<>`.
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        Self::read_max(r, u32::MAX)
+    }
+
+    /// Reads `opaque identifier<m>`, failing if more than max bytes follow.
+    pub fn read_max(r: &mut Reader, max: u32) -> Result<Self> {
+        let len = read_len(r, max).decoding("xdr::Opaque")?;
+        Self::read_fixed(r, len)
+    }
+
+    /// Reads `opaque identifier[n]`.
+    pub fn read_fixed(r: &mut Reader, len: usize) -> Result<Self> {
+        Ok(Opaque(read_padded(r, len).decoding("xdr::Opaque")?))
+    }
+
+    /// Writes `opaque identifier<>`.
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        self.write_max(w, u32::MAX)
+    }
+
+    /// Writes `opaque identifier<m>`, failing if there are more than max
+    /// bytes.
+    pub fn write_max(&self, w: &mut Writer, max: u32) -> Result<()> {
+        write_len(w, self.0.len(), max)?;
+        self.write_fixed(w)
+    }
+
+    /// Writes `opaque identifier[n]`.
+    pub fn write_fixed(&self, w: &mut Writer) -> Result<()> {
+        write_padded(w, &self.0)
+    }
+}
+
+impl From<Opaque> for Vec<u8> {
+    fn from(x: Opaque) -> Vec<u8> { x.0 }
+}
+
+impl From<Vec<u8>> for Opaque {
+    fn from(x: Vec<u8>) -> Opaque { Opaque(x) }
+}
+
+/// Reads the length of variable-length data, enforcing its maximum.
+pub(crate) fn read_len(r: &mut Reader, max: u32) -> Result<usize> {
+    let position = r.position()?;
+    let len: u32 = U32::read(r)?.into();
+    if len > max {
+        return Err(Error::invalid(format!(
+            "length {} exceeds the maximum of {}", len, max
+        )).at(position))
+    }
+    len.try_into().map_err(Error::invalid).at(position)
+}
+
+/// Reads len bytes, then skips the residual bytes padding them to a multiple
+/// of four.
+pub(crate) fn read_padded(r: &mut Reader, len: usize) -> Result<Vec<u8>> {
+    let bytes = r.read_to(len, |x| x)?;
+    r.skip(((4 - len % 4) % 4) as u32)?;
+    Ok(bytes)
+}
+
+/// Writes the length of variable-length data, enforcing its maximum.
+pub(crate) fn write_len(w: &mut Writer, len: usize, max: u32) -> Result<()> {
+    match u32::try_from(len) {
+        Ok(len) if len <= max => U32::from(len).write(w),
+        _ => Err(Error::invalid(format!(
+            "length {} exceeds the maximum of {}", len, max
+        )).at(w.position()))
+    }
+}
+
+/// Writes the bytes, then the residual zero bytes padding them to a multiple
+/// of four.
+pub(crate) fn write_padded(w: &mut Writer, bytes: &[u8]) -> Result<()> {
+    w.write(bytes)?;
+    w.pad(((4 - bytes.len() % 4) % 4) as u32)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variable_length() -> Result<()> {
+        let mut r = Reader::from_bytes(&[0, 0, 0, 5, 1, 2, 3, 4, 5, 0, 0, 0, 9]);
+        assert_eq!(Vec::from(Opaque::read(&mut r)?), [1, 2, 3, 4, 5]);
+        // The residual bytes are skipped.
+        assert_eq!(r.position()?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_length() -> Result<()> {
+        let mut r = Reader::from_bytes(&[1, 2, 3, 0, 4, 5, 6, 7]);
+        assert_eq!(Vec::from(Opaque::read_fixed(&mut r, 3)?), [1, 2, 3]);
+        assert_eq!(r.position()?, 4);
+        assert_eq!(Vec::from(Opaque::read_fixed(&mut r, 4)?), [4, 5, 6, 7]);
+        assert_eq!(r.position()?, 8);
+        assert!(Vec::from(Opaque::read_fixed(&mut r, 0)?).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn over_max() {
+        let bytes = [0, 0, 0, 5, 1, 2, 3, 4, 5, 0, 0, 0];
+        let err = Opaque::read_max(&mut Reader::from_bytes(&bytes), 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "length 5 exceeds the maximum of 4 while decoding xdr::Opaque at offset 0x0"
+        );
+        assert!(Opaque::read_max(&mut Reader::from_bytes(&bytes), 5).is_ok());
+    }
+
+    #[test]
+    fn write() -> Result<()> {
+        let x = Opaque::from(vec![1, 2, 3, 4, 5]);
+        assert_eq!(Writer::to_vec(|w| x.write(w))?, [0, 0, 0, 5, 1, 2, 3, 4, 5, 0, 0, 0]);
+        assert_eq!(Writer::to_vec(|w| x.write_fixed(w))?, [1, 2, 3, 4, 5, 0, 0, 0]);
+        let x = Opaque::from(vec![1, 2, 3, 4]);
+        assert_eq!(Writer::to_vec(|w| x.write_fixed(w))?, [1, 2, 3, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn write_over_max() {
+        let x = Opaque::from(vec![1, 2, 3, 4, 5]);
+        let err = Writer::to_vec(|w| x.write_max(w, 4)).unwrap_err();
+        assert_eq!(err.to_string(), "length 5 exceeds the maximum of 4 at offset 0x0");
+        assert!(Writer::to_vec(|w| x.write_max(w, 5)).is_ok());
+    }
+
+    #[test]
+    fn hostile_length() {
+        let bytes = [0xff, 0xff, 0xff, 0xff, 1, 2, 3, 4];
+        assert!(Opaque::read(&mut Reader::from_bytes(&bytes)).is_err());
+        let bytes = [0, 0, 0, 5, 1, 2, 3, 4, 5, 0, 0, 0];
+        let mut r = Reader::from_bytes(&bytes).with_max_alloc(4);
+        let err = Opaque::read(&mut r).unwrap_err();
+        assert!(err.to_string().starts_with("can't allocate 5 bytes, the limit is 4"));
+    }
+
+    #[test]
+    fn truncated() {
+        // Padding is skipped rather than read, so only cutting the length or
+        // the data itself is noticed.
+        let bytes = [0, 0, 0, 5, 1, 2, 3, 4, 5];
+        for len in 0..bytes.len() {
+            assert!(Opaque::read(&mut Reader::from_bytes(&bytes[..len])).is_err());
+        }
+    }
+}
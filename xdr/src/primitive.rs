@@ -1,6 +1,6 @@
-use std::io::{Result};
+use std::result::Result as StdResult;
 
-use binary::Reader;
+use binary::{Context, Error, Reader, Result, Writer};
 
 /// Integer
 ///
@@ -9,13 +9,15 @@ use binary::Reader;
 /// two's complement notation.  The most and least significant bytes are
 /// 0 and 3, respectively.  Integers are declared as follows:
 ///
-///     int identifier;
+/// ```text
+/// int identifier;
 ///
-///       (MSB)                   (LSB)
-///     +-------+-------+-------+-------+
-///     |byte 0 |byte 1 |byte 2 |byte 3 |                      INTEGER
-///     +-------+-------+-------+-------+
-///     <------------32 bits------------>
+///   (MSB)                   (LSB)
+/// +-------+-------+-------+-------+
+/// |byte 0 |byte 1 |byte 2 |byte 3 |                      INTEGER
+/// +-------+-------+-------+-------+
+/// <------------32 bits------------>
+/// ```
 #[derive(Debug)]
 pub struct I32(i32);
 
@@ -24,18 +26,30 @@ pub const I32_SIZE: usize = 4;
 
 impl I32 {
     pub fn read(r: &mut Reader) -> Result<Self> {
-        r.read_as::<Self, I32_SIZE>(Self::decode)
+        r.read_as::<Self, I32_SIZE>(Self::decode).decoding("xdr::I32")
     }
 
     pub fn decode(x: &[u8; I32_SIZE]) -> Self {
         Self(i32::from_be_bytes(*x))
     }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; I32_SIZE] {
+        self.0.to_be_bytes()
+    }
 }
 
 impl From<I32> for i32 {
     fn from(I32(x): I32) -> Self { x }
 }
 
+impl From<i32> for I32 {
+    fn from(x: i32) -> Self { I32(x) }
+}
+
 /// Unsigned Integer
 ///
 /// An XDR unsigned integer is a 32-bit datum that encodes a nonnegative
@@ -43,13 +57,15 @@ impl From<I32> for i32 {
 /// unsigned binary number whose most and least significant bytes are 0
 /// and 3, respectively.  An unsigned integer is declared as follows:
 ///
-///     unsigned int identifier;
+/// ```text
+/// unsigned int identifier;
 ///
-///       (MSB)                   (LSB)
-///     +-------+-------+-------+-------+
-///     |byte 0 |byte 1 |byte 2 |byte 3 |             UNSIGNED INTEGER
-///     +-------+-------+-------+-------+
-///     <------------32 bits------------>
+///   (MSB)                   (LSB)
+/// +-------+-------+-------+-------+
+/// |byte 0 |byte 1 |byte 2 |byte 3 |             UNSIGNED INTEGER
+/// +-------+-------+-------+-------+
+/// <------------32 bits------------>
+/// ```
 #[derive(Debug)]
 pub struct U32(u32);
 
@@ -58,18 +74,30 @@ pub const U32_SIZE: usize = 4;
 
 impl U32 {
     pub fn read(r: &mut Reader) -> Result<Self> {
-        r.read_as::<Self, U32_SIZE>(Self::decode)
+        r.read_as::<Self, U32_SIZE>(Self::decode).decoding("xdr::U32")
     }
 
     pub fn decode(x: &[u8; U32_SIZE]) -> Self {
         Self(u32::from_be_bytes(*x))
     }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; U32_SIZE] {
+        self.0.to_be_bytes()
+    }
 }
 
 impl From<U32> for u32 {
     fn from(U32(x): U32) -> Self { x }
 }
 
+impl From<u32> for U32 {
+    fn from(x: u32) -> Self { U32(x) }
+}
+
 /// Hyper Integer
 ///
 /// The standard also defines 64-bit (8-byte) numbers called hyper
@@ -78,14 +106,16 @@ impl From<U32> for u32 {
 /// notation.  The most and least significant bytes are 0 and 7,
 /// respectively.  Their declarations:
 ///
-///     hyper identifier;
+/// ```text
+/// hyper identifier;
 ///
-///       (MSB)                                                   (LSB)
-///     +-------+-------+-------+-------+-------+-------+-------+-------+
-///     |byte 0 |byte 1 |byte 2 |byte 3 |byte 4 |byte 5 |byte 6 |byte 7 |
-///     +-------+-------+-------+-------+-------+-------+-------+-------+
-///     <----------------------------64 bits---------------------------->
-///                                                HYPER INTEGER
+///   (MSB)                                                   (LSB)
+/// +-------+-------+-------+-------+-------+-------+-------+-------+
+/// |byte 0 |byte 1 |byte 2 |byte 3 |byte 4 |byte 5 |byte 6 |byte 7 |
+/// +-------+-------+-------+-------+-------+-------+-------+-------+
+/// <----------------------------64 bits---------------------------->
+///                                            HYPER INTEGER
+/// ```
 #[derive(Debug)]
 pub struct I64(i64);
 
@@ -94,18 +124,30 @@ pub const I64_SIZE: usize = 8;
 
 impl I64 {
     pub fn read(r: &mut Reader) -> Result<Self> {
-        r.read_as::<Self, I64_SIZE>(Self::decode)
+        r.read_as::<Self, I64_SIZE>(Self::decode).decoding("xdr::I64")
     }
 
     pub fn decode(x: &[u8; I64_SIZE]) -> Self {
         Self(i64::from_be_bytes(*x))
     }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; I64_SIZE] {
+        self.0.to_be_bytes()
+    }
 }
 
 impl From<I64> for i64 {
     fn from(I64(x): I64) -> Self { x }
 }
 
+impl From<i64> for I64 {
+    fn from(x: i64) -> Self { I64(x) }
+}
+
 /// ## Unsigned Hyper Integer
 ///
 /// The standard also defines 64-bit (8-byte) numbers called unsigned
@@ -114,14 +156,16 @@ impl From<I64> for i64 {
 /// notation.  The most and least significant bytes are 0 and 7,
 /// respectively.  Their declarations:
 ///
-///     unsigned hyper identifier;
+/// ```text
+/// unsigned hyper identifier;
 ///
-///       (MSB)                                                   (LSB)
-///     +-------+-------+-------+-------+-------+-------+-------+-------+
-///     |byte 0 |byte 1 |byte 2 |byte 3 |byte 4 |byte 5 |byte 6 |byte 7 |
-///     +-------+-------+-------+-------+-------+-------+-------+-------+
-///     <----------------------------64 bits---------------------------->
-///                                                UNSIGNED HYPER INTEGER
+///   (MSB)                                                   (LSB)
+/// +-------+-------+-------+-------+-------+-------+-------+-------+
+/// |byte 0 |byte 1 |byte 2 |byte 3 |byte 4 |byte 5 |byte 6 |byte 7 |
+/// +-------+-------+-------+-------+-------+-------+-------+-------+
+/// <----------------------------64 bits---------------------------->
+///                                            UNSIGNED HYPER INTEGER
+/// ```
 #[derive(Debug)]
 pub struct U64(u64);
 
@@ -130,14 +174,402 @@ pub const U64_SIZE: usize = 8;
 
 impl U64 {
     pub fn read(r: &mut Reader) -> Result<Self> {
-        r.read_as::<Self, U64_SIZE>(Self::decode)
+        r.read_as::<Self, U64_SIZE>(Self::decode).decoding("xdr::U64")
     }
 
     pub fn decode(x: &[u8; U64_SIZE]) -> Self {
         Self(u64::from_be_bytes(*x))
     }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; U64_SIZE] {
+        self.0.to_be_bytes()
+    }
 }
 
 impl From<U64> for u64 {
     fn from(U64(x): U64) -> Self { x }
 }
+
+impl From<u64> for U64 {
+    fn from(x: u64) -> Self { U64(x) }
+}
+
+/// Boolean
+///
+/// Booleans are important enough and occur frequently enough to warrant
+/// their own explicit type in the standard.  Booleans are declared as
+/// follows:
+///
+/// ```text
+/// bool identifier;
+/// ```
+///
+/// This is equivalent to:
+///
+/// ```text
+/// enum { FALSE = 0, TRUE = 1 } identifier;
+/// ```
+#[derive(Debug)]
+pub struct Bool(bool);
+
+const BOOL_SIZE: usize = 4;
+
+impl Bool {
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        r.try_read_as::<Self, Error, BOOL_SIZE>(|x| {
+            Self::decode(x).map_err(|n| Error::invalid(format!("invalid boolean {}", n)))
+        }).decoding("xdr::Bool")
+    }
+
+    pub fn decode(x: &[u8; BOOL_SIZE]) -> StdResult<Self, i32> {
+        match i32::from_be_bytes(*x) {
+            0 => Ok(Self(false)),
+            1 => Ok(Self(true)),
+            n => Err(n),
+        }
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; BOOL_SIZE] {
+        i32::from(self.0).to_be_bytes()
+    }
+}
+
+impl From<Bool> for bool {
+    fn from(Bool(x): Bool) -> Self { x }
+}
+
+impl From<bool> for Bool {
+    fn from(x: bool) -> Self { Bool(x) }
+}
+
+/// Floating-Point
+///
+/// The standard defines the floating-point data type "float" (32 bits or
+/// 4 bytes).  The encoding used is the IEEE standard for normalized
+/// single-precision floating-point numbers \[IEEE\].  The following three
+/// fields describe the single-precision floating-point number:
+///
+/// S: The sign of the number.  Values 0 and 1 represent positive and
+///    negative, respectively.  One bit.
+///
+/// E: The exponent of the number, base 2.  8 bits are devoted to this
+///    field.  The exponent is biased by 127.
+///
+/// F: The fractional part of the number's mantissa, base 2.  23 bits
+///    are devoted to this field.
+///
+/// Therefore, the floating-point number is described by:
+///
+/// ```text
+/// (-1)**S * 2**(E-Bias) * 1.F
+/// ```
+///
+/// It is declared as follows:
+///
+/// ```text
+/// float identifier;
+///
+/// +-------+-------+-------+-------+
+/// |byte 0 |byte 1 |byte 2 |byte 3 |              SINGLE-PRECISION
+/// S|   E   |           F          |         FLOATING-POINT NUMBER
+/// +-------+-------+-------+-------+
+/// 1|<- 8 ->|<-------23 bits------>|
+/// <------------32 bits------------>
+/// ```
+///
+/// The IEEE specifications should be consulted concerning the encoding
+/// for signed zero, signed infinity (overflow), and denormalized numbers
+/// (underflow) \[IEEE\].  According to IEEE specifications, the "NaN" (not
+/// a number) is system dependent and should not be interpreted within
+/// XDR as anything other than "NaN".
+#[derive(Debug)]
+pub struct Float(f32);
+
+const FLOAT_SIZE: usize = 4;
+
+impl Float {
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        r.read_as::<Self, FLOAT_SIZE>(Self::decode).decoding("xdr::Float")
+    }
+
+    pub fn decode(x: &[u8; FLOAT_SIZE]) -> Self {
+        Self(f32::from_be_bytes(*x))
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; FLOAT_SIZE] {
+        self.0.to_be_bytes()
+    }
+}
+
+impl From<Float> for f32 {
+    fn from(Float(x): Float) -> Self { x }
+}
+
+impl From<f32> for Float {
+    fn from(x: f32) -> Self { Float(x) }
+}
+
+/// Double-Precision Floating-Point
+///
+/// The standard defines the encoding for the double-precision floating-
+/// point data type "double" (64 bits or 8 bytes).  The encoding used is
+/// the IEEE standard for normalized double-precision floating-point
+/// numbers \[IEEE\].  The standard encodes the following three fields,
+/// which describe the double-precision floating-point number:
+///
+/// S: The sign of the number.  Values 0 and 1 represent positive and
+///    negative, respectively.  One bit.
+///
+/// E: The exponent of the number, base 2.  11 bits are devoted to
+///    this field.  The exponent is biased by 1023.
+///
+/// F: The fractional part of the number's mantissa, base 2.  52 bits
+///    are devoted to this field.
+///
+/// Therefore, the floating-point number is described by:
+///
+/// ```text
+/// (-1)**S * 2**(E-Bias) * 1.F
+/// ```
+///
+/// It is declared as follows:
+///
+/// ```text
+/// double identifier;
+///
+/// +------+------+------+------+------+------+------+------+
+/// |byte 0|byte 1|byte 2|byte 3|byte 4|byte 5|byte 6|byte 7|
+/// S|    E   |                    F                        |
+/// +------+------+------+------+------+------+------+------+
+/// 1|<--11-->|<-----------------52 bits------------------->|
+/// <-----------------------64 bits------------------------->
+///                                DOUBLE-PRECISION FLOATING-POINT
+/// ```
+#[derive(Debug)]
+pub struct Double(f64);
+
+const DOUBLE_SIZE: usize = 8;
+
+impl Double {
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        r.read_as::<Self, DOUBLE_SIZE>(Self::decode).decoding("xdr::Double")
+    }
+
+    pub fn decode(x: &[u8; DOUBLE_SIZE]) -> Self {
+        Self(f64::from_be_bytes(*x))
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; DOUBLE_SIZE] {
+        self.0.to_be_bytes()
+    }
+}
+
+impl From<Double> for f64 {
+    fn from(Double(x): Double) -> Self { x }
+}
+
+impl From<f64> for Double {
+    fn from(x: f64) -> Self { Double(x) }
+}
+
+/// Quadruple-Precision Floating-Point
+///
+/// The standard defines the encoding for the quadruple-precision
+/// floating-point data type "quadruple" (128 bits or 16 bytes).  The
+/// encoding used is designed to be a simple analog of the encoding used
+/// for single- and double-precision floating-point numbers using one
+/// form of IEEE double extended precision.  The standard encodes the
+/// following three fields, which describe the quadruple-precision
+/// floating-point number:
+///
+/// S: The sign of the number.  Values 0 and 1 represent positive and
+///    negative, respectively.  One bit.
+///
+/// E: The exponent of the number, base 2.  15 bits are devoted to
+///    this field.  The exponent is biased by 16383.
+///
+/// F: The fractional part of the number's mantissa, base 2.  112 bits
+///    are devoted to this field.
+///
+/// Therefore, the floating-point number is described by:
+///
+/// ```text
+/// (-1)**S * 2**(E-Bias) * 1.F
+/// ```
+///
+/// It is declared as follows:
+///
+/// ```text
+/// quadruple identifier;
+///
+/// +------+------+------+------+------+------+-...--+------+
+/// |byte 0|byte 1|byte 2|byte 3|byte 4|byte 5| ...  |byte15|
+/// S|    E       |                  F                      |
+/// +------+------+------+------+------+------+-...--+------+
+/// 1|<----15---->|<-------------112 bits------------------>|
+/// <-----------------------128 bits------------------------>
+///                              QUADRUPLE-PRECISION FLOATING-POINT
+/// ```
+///
+/// Rust has no stable 128 bits float type, so the value is only available as
+/// its bit pattern.
+#[derive(Debug)]
+pub struct Quadruple(u128);
+
+const QUADRUPLE_SIZE: usize = 16;
+
+impl Quadruple {
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        r.read_as::<Self, QUADRUPLE_SIZE>(Self::decode).decoding("xdr::Quadruple")
+    }
+
+    pub fn decode(x: &[u8; QUADRUPLE_SIZE]) -> Self {
+        Self(u128::from_be_bytes(*x))
+    }
+
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        w.write_as(self, Self::encode)
+    }
+
+    pub fn encode(&self) -> [u8; QUADRUPLE_SIZE] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_bits(x: u128) -> Self {
+        Self(x)
+    }
+
+    pub fn to_bits(&self) -> u128 {
+        self.0
+    }
+}
+
+/// Void
+///
+/// An XDR void is a 0-byte quantity.  Voids are useful for describing
+/// operations that take no data as input or no data as output.  They are
+/// also useful in unions, where some arms may contain data and others do
+/// not.  The declaration is simply as follows:
+///
+/// ```text
+/// void;
+/// ```
+///
+/// Voids are illustrated as follows:
+///
+/// ```text
+///   ++
+///   ||                                                     VOID
+///   ++
+/// --><-- 0 bytes
+/// ```
+#[derive(Debug)]
+pub struct Void;
+
+impl Void {
+    pub fn read(_r: &mut Reader) -> Result<Self> {
+        Ok(Void)
+    }
+
+    pub fn decode(_x: &[u8; 0]) -> Self {
+        Void
+    }
+
+    pub fn write(&self, _w: &mut Writer) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn encode(&self) -> [u8; 0] {
+        []
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bool() -> Result<()> {
+        let mut r = Reader::from_bytes(&[0, 0, 0, 0, 0, 0, 0, 1]);
+        assert!(!bool::from(Bool::read(&mut r)?));
+        assert!(bool::from(Bool::read(&mut r)?));
+        for n in [2i32, -1, 0x100] {
+            let bytes = n.to_be_bytes();
+            let err = Bool::read(&mut Reader::from_bytes(&bytes)).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!("invalid boolean {} while decoding xdr::Bool at offset 0x0", n)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn floats() -> Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend((-1.5f32).to_be_bytes());
+        bytes.extend(std::f64::consts::PI.to_be_bytes());
+        let mut r = Reader::from_bytes(&bytes);
+        assert_eq!(f32::from(Float::read(&mut r)?), -1.5);
+        assert_eq!(f64::from(Double::read(&mut r)?), std::f64::consts::PI);
+
+        let nan = f64::NAN.to_be_bytes();
+        assert!(f64::from(Double::read(&mut Reader::from_bytes(&nan))?).is_nan());
+        Ok(())
+    }
+
+    #[test]
+    fn quadruple() -> Result<()> {
+        // 1.0 in IEEE 754 quadruple precision.
+        let bytes = [0x3f, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let x = Quadruple::read(&mut Reader::from_bytes(&bytes))?;
+        assert_eq!(x.to_bits(), 0x3fff << 112);
+        assert!(Quadruple::read(&mut Reader::from_bytes(&bytes[..15])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn void() -> Result<()> {
+        let mut r = Reader::from_bytes(&[1]);
+        Void::read(&mut r)?;
+        assert_eq!(r.position()?, 0);
+        assert!(Writer::to_vec(|w| Void.write(w))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<()> {
+        let quadruple = Quadruple::from_bits(0x3fff << 112 | 1);
+        let bytes = Writer::to_vec(|w| {
+            Bool::from(true).write(w)?;
+            Float::from(-1.5).write(w)?;
+            Double::from(std::f64::consts::PI).write(w)?;
+            quadruple.write(w)
+        })?;
+        assert_eq!(bytes.len(), 4 + 4 + 8 + 16);
+        assert_eq!(bytes[..4], [0, 0, 0, 1]);
+
+        let mut r = Reader::from_bytes(&bytes);
+        assert!(bool::from(Bool::read(&mut r)?));
+        assert_eq!(f32::from(Float::read(&mut r)?), -1.5);
+        assert_eq!(f64::from(Double::read(&mut r)?), std::f64::consts::PI);
+        assert_eq!(Quadruple::read(&mut r)?.to_bits(), quadruple.to_bits());
+        Ok(())
+    }
+}
@@ -1,7 +1,9 @@
-use std::io::{Error, Result};
+use std::borrow::Cow;
 use std::string::String as StdString;
 
-use binary::Reader;
+use binary::{Context, Error, Reader, Result, Writer};
+
+use crate::opaque::{read_len, read_padded, write_len, write_padded};
 
 /// String
 ///
@@ -14,9 +16,15 @@ use binary::Reader;
 /// bytes, r, to make the total byte count a multiple of four.  Counted
 /// byte strings are declared as follows:
 ///
-///     string object<m>;
+/// ```text
+/// string object<m>;
+/// ```
+///
 /// or
-///     string object<>;
+///
+/// ```text
+/// string object<>;
+/// ```
 ///
 /// The constant m denotes an upper bound of the number of bytes that a
 /// string may contain.  If m is not specified, as in the second
@@ -25,46 +33,181 @@ use binary::Reader;
 /// For example, a filing protocol may state that a file name can be no
 /// longer than 255 bytes, as follows:
 ///
-///     string filename<255>;
+/// ```text
+/// string filename<255>;
 ///
-///        0     1     2     3     4     5   ...
-///     +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
-///     |        length n       |byte0|byte1|...| n-1 |  0  |...|  0  |
-///     +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
-///     |<-------4 bytes------->|<------n bytes------>|<---r bytes--->|
-///                             |<----n+r (where (n+r) mod 4 = 0)---->|
-///                                                              STRING
+///    0     1     2     3     4     5   ...
+/// +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
+/// |        length n       |byte0|byte1|...| n-1 |  0  |...|  0  |
+/// +-----+-----+-----+-----+-----+-----+...+-----+-----+...+-----+
+/// |<-------4 bytes------->|<------n bytes------>|<---r bytes--->|
+///                         |<----n+r (where (n+r) mod 4 = 0)---->|
+///                                                          STRING
+/// ```
 ///
 /// It is an error to encode a length greater than the maximum described
 /// in the specification.
 #[derive(Debug)]
 pub struct String(StdString);
 
-const STRING_LEN_SIZE: usize = 4;
-
 impl String {
+    /// Reads `string object<>`.
     pub fn read(r: &mut Reader) -> Result<Self> {
-        let len: u32 = r.read_as::<u32, STRING_LEN_SIZE>(Self::decode_len)?;
-        let len: usize = len.try_into().expect(&format!(
-            "can't fit string length of {} into a usize", len
-        ));
-        let s = String(r.try_read_to::<StdString, Error>(len, Self::decode_str)?);
-        r.align(4)?;
-        Ok(s)
+        Self::read_max(r, u32::MAX)
+    }
+
+    /// Reads `string object<m>`, failing if more than max bytes follow.
+    pub fn read_max(r: &mut Reader, max: u32) -> Result<Self> {
+        Self::read_string(r, max).decoding("xdr::String")
+    }
+
+    fn read_string(r: &mut Reader, max: u32) -> Result<Self> {
+        let len = read_len(r, max)?;
+        let position = r.position()?;
+        let bytes = read_padded(r, len)?;
+        Ok(String(StdString::from_utf8(bytes).map_err(Error::invalid).at(position)?))
     }
 
-    fn decode_len(x: &[u8; STRING_LEN_SIZE]) -> u32 {
-        u32::from_be_bytes(*x)
+    /// Writes `string object<>`.
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        self.write_max(w, u32::MAX)
     }
 
-    fn decode_str(x: Vec<u8>) -> Result<StdString> {
-        match StdString::from_utf8(x) {
-            Ok(s) => Ok(s),
-            Err(e) => Err(Error::other(e)) // TODO: maybe use InvalidInput?
-        }
+    /// Writes `string object<m>`, failing if there are more than max bytes.
+    pub fn write_max(&self, w: &mut Writer, max: u32) -> Result<()> {
+        write_len(w, self.0.len(), max)?;
+        write_padded(w, self.0.as_bytes())
     }
 }
 
 impl From<String> for StdString {
     fn from(x: String) -> StdString { x.0 }
 }
+
+impl From<StdString> for String {
+    fn from(x: StdString) -> String { String(x) }
+}
+
+impl From<&str> for String {
+    fn from(x: &str) -> String { String(x.to_string()) }
+}
+
+/// String of arbitrary bytes
+///
+/// The standard calls strings ASCII, but in practice they are counted byte
+/// strings in whatever encoding their writer used. ByteString decodes them
+/// as such, where String would reject bytes that are not valid UTF-8.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ByteString(Vec<u8>);
+
+impl ByteString {
+    /// Reads `string object<>`.
+    pub fn read(r: &mut Reader) -> Result<Self> {
+        Self::read_max(r, u32::MAX)
+    }
+
+    /// Reads `string object<m>`, failing if more than max bytes follow.
+    pub fn read_max(r: &mut Reader, max: u32) -> Result<Self> {
+        let len = read_len(r, max).decoding("xdr::ByteString")?;
+        Ok(ByteString(read_padded(r, len).decoding("xdr::ByteString")?))
+    }
+
+    /// Writes `string object<>`.
+    pub fn write(&self, w: &mut Writer) -> Result<()> {
+        self.write_max(w, u32::MAX)
+    }
+
+    /// Writes `string object<m>`, failing if there are more than max bytes.
+    pub fn write_max(&self, w: &mut Writer, max: u32) -> Result<()> {
+        write_len(w, self.0.len(), max)?;
+        write_padded(w, &self.0)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Replaces invalid UTF-8 sequences with U+FFFD REPLACEMENT CHARACTER.
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        StdString::from_utf8_lossy(&self.0)
+    }
+}
+
+impl From<ByteString> for Vec<u8> {
+    fn from(x: ByteString) -> Vec<u8> { x.0 }
+}
+
+impl From<Vec<u8>> for ByteString {
+    fn from(x: Vec<u8>) -> ByteString { ByteString(x) }
+}
+
+impl From<String> for ByteString {
+    fn from(x: String) -> ByteString { ByteString(x.0.into_bytes()) }
+}
+
+impl From<StdString> for ByteString {
+    fn from(x: StdString) -> ByteString { ByteString(x.into_bytes()) }
+}
+
+impl From<&str> for ByteString {
+    fn from(x: &str) -> ByteString { ByteString(x.as_bytes().to_vec()) }
+}
+
+impl TryFrom<ByteString> for String {
+    type Error = Error;
+
+    fn try_from(x: ByteString) -> Result<String> {
+        Ok(String(StdString::from_utf8(x.0).map_err(Error::invalid)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOT_UTF8: [u8; 8] = [0, 0, 0, 3, b'a', 0xff, b'b', 0];
+
+    #[test]
+    fn read() -> Result<()> {
+        let mut r = Reader::from_bytes(&[0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', 0, 0, 0]);
+        assert_eq!(StdString::from(String::read(&mut r)?), "hello");
+        assert_eq!(r.position()?, 12);
+        Ok(())
+    }
+
+    #[test]
+    fn not_utf8() -> Result<()> {
+        let err = String::read(&mut Reader::from_bytes(&NOT_UTF8)).unwrap_err();
+        assert_eq!(err.offset(), Some(4));
+        assert_eq!(err.type_name(), Some("xdr::String"));
+
+        let mut r = Reader::from_bytes(&NOT_UTF8);
+        let x = ByteString::read(&mut r)?;
+        assert_eq!(r.position()?, 8);
+        assert_eq!(x.as_bytes(), b"a\xffb");
+        assert_eq!(x.to_string_lossy(), "a\u{fffd}b");
+        assert!(String::try_from(x.clone()).is_err());
+        assert_eq!(Writer::to_vec(|w| x.write(w))?, NOT_UTF8);
+        Ok(())
+    }
+
+    #[test]
+    fn over_max() {
+        let bytes = [0, 0, 0, 5, b'h', b'e', b'l', b'l', b'o', 0, 0, 0];
+        let err = String::read_max(&mut Reader::from_bytes(&bytes), 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "length 5 exceeds the maximum of 4 while decoding xdr::String at offset 0x0"
+        );
+        let err = ByteString::read_max(&mut Reader::from_bytes(&bytes), 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "length 5 exceeds the maximum of 4 while decoding xdr::ByteString at offset 0x0"
+        );
+        assert!(ByteString::read_max(&mut Reader::from_bytes(&bytes), 5).is_ok());
+
+        assert!(Writer::to_vec(|w| String::from("hello").write_max(w, 4)).is_err());
+        assert!(Writer::to_vec(|w| ByteString::from("hello").write_max(w, 4)).is_err());
+        assert!(Writer::to_vec(|w| ByteString::from("hello").write_max(w, 5)).is_ok());
+    }
+}
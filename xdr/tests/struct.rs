@@ -0,0 +1,87 @@
+use binary::{Reader, Result, Writer};
+use xdr::{Array, String, Struct, I32, U32};
+
+#[derive(Debug, Struct)]
+struct Limits {
+    #[xdr(max = 4)]
+    name: String,
+    #[xdr(max = 2)]
+    ids: Array<U32>,
+    matrix: Array<Array<I32>>,
+    #[xdr(max = 2)]
+    rows: Array<Array<U32>>,
+}
+
+const EMPTY: [u8; 16] = [0; 16];
+
+fn ints<T, U: From<T>>(xs: Array<T>) -> Vec<U> {
+    Vec::from(xs).into_iter().map(U::from).collect()
+}
+
+const BYTES: [u8; 60] = [
+    0, 0, 0, 4, b'p', b'o', b'o', b'l',
+    0, 0, 0, 2, 0, 0, 0, 7, 0, 0, 0, 8,
+    0, 0, 0, 3,
+        0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff,
+        0, 0, 0, 0,
+        0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 3,
+    0, 0, 0, 1,
+        0, 0, 0, 1, 0, 0, 0, 9,
+];
+
+#[test]
+fn read() -> Result<()> {
+    let x = Limits::read(&mut Reader::from_bytes(&BYTES))?;
+    assert_eq!(std::string::String::from(x.name), "pool");
+    assert_eq!(ints::<_, u32>(x.ids), [7, 8]);
+    let matrix: Vec<Vec<i32>> = Vec::from(x.matrix).into_iter().map(ints).collect();
+    assert_eq!(matrix, [vec![-1], vec![], vec![2, 3]]);
+    let rows: Vec<Vec<u32>> = Vec::from(x.rows).into_iter().map(ints).collect();
+    assert_eq!(rows, [vec![9]]);
+    Ok(())
+}
+
+#[test]
+fn round_trip() -> Result<()> {
+    let x = Limits::read(&mut Reader::from_bytes(&BYTES))?;
+    assert_eq!(Writer::to_vec(|w| x.write(w))?, BYTES);
+    Ok(())
+}
+
+// Lengths are checked before reading what follows: the errors point at them.
+#[test]
+fn read_over_max() {
+    for (offset, field) in [(0, "name"), (4, "ids"), (12, "rows")] {
+        let mut bytes = EMPTY;
+        bytes[offset + 3] = 5;
+        let e = Limits::read(&mut Reader::from_bytes(&bytes)).unwrap_err();
+        assert_eq!(e.offset(), Some(offset as u64));
+        assert_eq!(e.path().next(), Some(&binary::Segment::Name(field.into())));
+    }
+}
+
+// Errors in nested arrays locate the element they happened in.
+#[test]
+fn nested_error_path() {
+    // The second element of the first row of the matrix is missing.
+    let bytes = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1];
+    let e = Limits::read(&mut Reader::from_bytes(&bytes)).unwrap_err();
+    assert_eq!(e.path().collect::<Vec<_>>(), [
+        &binary::Segment::Name("matrix".into()),
+        &binary::Segment::Index(0),
+        &binary::Segment::Index(1),
+    ]);
+}
+
+#[test]
+fn write_over_max() {
+    let x = Limits {
+        name: "pools".into(),
+        ids: Array::from(vec![]),
+        matrix: Array::from(vec![]),
+        rows: Array::from(vec![]),
+    };
+    let e = Writer::to_vec(|w| x.write(w)).unwrap_err();
+    assert_eq!(e.offset(), Some(0));
+    assert_eq!(e.path().next(), Some(&binary::Segment::Name("name".into())));
+}
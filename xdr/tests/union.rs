@@ -0,0 +1,101 @@
+use binary::{Reader, Result, Writer};
+use xdr::{Array, Enum, Opaque, String, Union, I64, U32};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Enum)]
+enum Kind {
+    Void = 0,
+    Pair = 1,
+    Named = 2,
+}
+
+#[derive(Debug, Union)]
+#[repr(i32)]
+enum Arm {
+    Unit = Kind::Void as i32,
+    Tuple(U32, I64) = Kind::Pair as i32,
+    Named {
+        #[xdr(max = 4)]
+        name: String,
+        ids: Array<U32>,
+    } = Kind::Named as i32,
+    #[xdr(default)]
+    Other(i32, Opaque),
+}
+
+// Without a default arm, other discriminants are invalid.
+#[derive(Debug, Union)]
+#[repr(u32)]
+enum Strict {
+    Zero = 0,
+    Big(U32) = 0x80000000,
+}
+
+// Reads the whole of bytes, checking that writing back gives the same bytes.
+fn read(bytes: &[u8]) -> Result<Arm> {
+    let mut r = Reader::from_bytes(bytes);
+    let arm = Arm::read(&mut r)?;
+    assert_eq!(r.position()?, bytes.len() as u64);
+    assert_eq!(Writer::to_vec(|w| arm.write(w))?, bytes);
+    Ok(arm)
+}
+
+#[test]
+fn unit() -> Result<()> {
+    assert!(matches!(read(&[0, 0, 0, 0])?, Arm::Unit));
+    Ok(())
+}
+
+#[test]
+fn tuple() -> Result<()> {
+    let bytes = [0, 0, 0, 1, 0, 0, 0, 7, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe];
+    let Arm::Tuple(a, b) = read(&bytes)? else { panic!("not a tuple") };
+    assert_eq!((u32::from(a), i64::from(b)), (7, -2));
+    Ok(())
+}
+
+#[test]
+fn named() -> Result<()> {
+    let bytes = [0, 0, 0, 2, 0, 0, 0, 3, b'a', b'b', b'c', 0, 0, 0, 0, 1, 0, 0, 0, 9];
+    let Arm::Named { name, ids } = read(&bytes)? else { panic!("not named") };
+    assert_eq!(std::string::String::from(name), "abc");
+    assert_eq!(Vec::from(ids).into_iter().map(u32::from).collect::<Vec<_>>(), [9]);
+    Ok(())
+}
+
+// The default arm keeps the discriminant it was read with.
+#[test]
+fn default() -> Result<()> {
+    let bytes = [0xff, 0xff, 0xff, 0xfb, 0, 0, 0, 2, 1, 2, 0, 0];
+    let Arm::Other(d, x) = read(&bytes)? else { panic!("not the default") };
+    assert_eq!((d, Vec::from(x)), (-5, vec![1, 2]));
+    Ok(())
+}
+
+#[test]
+fn arm_over_max() {
+    let bytes = [0, 0, 0, 2, 0, 0, 0, 5, b'a', b'b', b'c', b'd', b'e', 0, 0, 0, 0, 0, 0, 0];
+    let e = read(&bytes).unwrap_err();
+    assert_eq!(e.offset(), Some(4));
+    assert_eq!(e.path().next(), Some(&binary::Segment::Name("name".into())));
+
+    let arm = Arm::Named { name: "abcde".into(), ids: Array::from(vec![]) };
+    let e = Writer::to_vec(|w| arm.write(w)).unwrap_err();
+    assert_eq!(e.offset(), Some(4));
+    assert_eq!(e.path().next(), Some(&binary::Segment::Name("name".into())));
+}
+
+#[test]
+fn unsigned_discriminant() -> Result<()> {
+    let bytes = [0x80, 0, 0, 0, 0, 0, 0, 1];
+    assert!(matches!(Strict::read(&mut Reader::from_bytes(&bytes))?, Strict::Big(_)));
+    assert!(matches!(Strict::read(&mut Reader::from_bytes(&[0; 4]))?, Strict::Zero));
+    assert_eq!(Writer::to_vec(|w| Strict::Big(1.into()).write(w))?, bytes);
+    Ok(())
+}
+
+#[test]
+fn invalid_discriminant() {
+    let e = Strict::read(&mut Reader::from_bytes(&[0, 0, 0, 1])).unwrap_err();
+    assert_eq!(e.offset(), Some(0));
+    assert_eq!(e.type_name(), Some("Strict"));
+}
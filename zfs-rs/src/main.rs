@@ -1,23 +1,125 @@
 use std::path::PathBuf;
+use std::process::exit;
 
 use clap::Parser;
 
+use zfs::uberblock::Uberblock;
 use zfs::vdev::physical::Physical;
-use zfs::vdev::label::LabelNumber;
+use zfs::vdev::label::{Labels, Rings};
 
 #[derive(Debug, Parser)]
 #[command(about, author, version, long_about = None)]
 struct Args {
     #[arg(short, long)]
     device: PathBuf,
+    /// Print GUIDs in hexadecimal
+    #[arg(long)]
+    hex_guids: bool,
+    /// Read labels whose checksum doesn't match, with a warning
+    #[arg(long)]
+    ignore_checksums: bool,
+    /// Print the active uberblock
+    #[arg(short, long)]
+    uberblock: bool,
+    /// List every valid uberblock, newest first
+    #[arg(long)]
+    uberblocks: bool,
+    /// Print the uberblock that was active at this txg, like zpool import -T
+    #[arg(short = 'T', long)]
+    txg: Option<u64>,
 }
 
 fn main() {
     let args = Args::parse();
 
     let vdev = Physical::open(args.device).unwrap();
-    let label_nvlist = zfs::vdev::label::read_nvlist(&vdev, LabelNumber::L0);
+    let labels = Labels::read(&vdev, args.ignore_checksums);
+    for (number, label) in labels.iter() {
+        match label {
+            Ok(label) => if let Some(e) = label.checksum_error() {
+                eprintln!("warning: label {}: {}", number, e);
+            },
+            Err(e) => eprintln!("can't read label {}: {}", number, e),
+        }
+    }
+    let Some((number, label)) = labels.authoritative() else {
+        eprintln!("can't read any label");
+        exit(1)
+    };
+    let mismatches = labels.mismatches();
+    for mismatch in mismatches.iter() {
+        eprintln!("labels disagree: {}", mismatch);
+    }
+    let agreeing: Vec<String> = labels.iter()
+        .filter(|(n, label)| label.is_ok() && mismatches.iter().all(|m| m.label != *n))
+        .map(|(n, _)| n.to_string())
+        .collect();
 
     println!("{:?}", vdev);
-    println!("{:?}", label_nvlist);
+    println!("------------------------------------");
+    println!("LABEL {}", number);
+    println!("------------------------------------");
+    print!("{}", label.nvlist().printer().indent(4).hex_guids(args.hex_guids));
+    println!("    labels = {}", agreeing.join(" "));
+
+    if args.uberblock || args.uberblocks || args.txg.is_some() {
+        let ashift = label.ashift().unwrap_or_else(|e| {
+            eprintln!("can't find the ashift, assuming 9: {}", e);
+            9
+        });
+        let rings = Rings::read(&vdev, ashift);
+        for (number, ring) in rings.iter() {
+            if let Err(e) = ring {
+                eprintln!("can't read the uberblocks of label {}: {}", number, e);
+            }
+        }
+        if args.uberblocks {
+            println!("------------------------------------");
+            println!("Uberblocks");
+            println!("------------------------------------");
+            for e in rings.candidates() {
+                println!(
+                    "    txg = {}, timestamp = {}, label = {}, slot = {}",
+                    e.uberblock.txg, e.uberblock.timestamp, e.label, e.slot
+                );
+            }
+        }
+        if args.uberblock || args.txg.is_some() {
+            let entry = match args.txg {
+                Some(txg) => rings.rewind(txg),
+                None => rings.active(),
+            };
+            let Some(entry) = entry else {
+                eprintln!("can't find any valid uberblock");
+                exit(1)
+            };
+            println!("------------------------------------");
+            println!("Uberblock (label {}, slot {})", entry.label, entry.slot);
+            println!("------------------------------------");
+            print_uberblock(&entry.uberblock);
+        }
+    }
+}
+
+/// Prints an uberblock like `zdb -u`, without the date.
+fn print_uberblock(ub: &Uberblock) {
+    println!("    magic = {:016x}", zfs::uberblock::UBERBLOCK_MAGIC);
+    println!("    version = {}", ub.version);
+    println!("    txg = {}", ub.txg);
+    println!("    guid_sum = {}", ub.guid_sum);
+    println!("    timestamp = {}", ub.timestamp);
+    println!("    mmp_magic = {:016x}", ub.mmp_magic);
+    if ub.mmp_valid() {
+        println!("    mmp_delay = {}", ub.mmp_delay);
+        if let Some(seq) = ub.mmp_seq() {
+            println!("    mmp_seq = {}", seq);
+        }
+        if let Some(fail) = ub.mmp_fail_intervals() {
+            println!("    mmp_fail = {}", fail);
+        }
+        if let Some(interval) = ub.mmp_interval() {
+            println!("    mmp_write = {}", interval);
+        }
+    }
+    println!("    checkpoint_txg = {}", ub.checkpoint_txg);
 }
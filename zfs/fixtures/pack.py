@@ -0,0 +1,61 @@
+#!/usr/bin/env python3
+"""Packs the uberblock fixtures, independently of the zfs crate.
+
+Writes an uberblock_t in each byte order, in a 4K ring slot sealed with an
+embedded checksum (zio_eck_t) as if it were the first slot of the ring of
+label 0, at offset 128K of the vdev.
+
+Run from this directory: python3 pack.py
+"""
+
+import hashlib
+import struct
+
+UBERBLOCK_MAGIC = 0x00bab10c
+MMP_MAGIC = 0xa11cea11
+ZEC_MAGIC = 0x0210da7ab10c7a11
+
+SLOT_SIZE = 4096
+SLOT_OFFSET = 128 * 1024
+
+
+def blkptr():
+    words = [0] * 16
+    # DVA 0: vdev 1, asize 8 sectors, offset 0x400 sectors.
+    words[0] = (1 << 32) | 8
+    words[1] = 0x400
+    # blk_prop: little endian, level 0, type 11 (objset), SHA-256 (8),
+    # LZ4 (15), psize and lsize of 2 sectors.
+    words[6] = (1 << 63) | (11 << 48) | (8 << 40) | (15 << 32) | (1 << 16) | 1
+    words[9] = 0                 # blk_phys_birth
+    words[10] = 1234             # blk_birth
+    words[11] = 77               # blk_fill
+    words[12:16] = [1, 2, 3, 4]  # blk_cksum
+    return words
+
+
+def uberblock(e):
+    # Interval of 1000 ms, sequence number 3 and 10 fail intervals, all valid.
+    mmp_config = (10 << 48) | (3 << 32) | (1000 << 8) | 0x7
+    words = [UBERBLOCK_MAGIC, 5000, 1234, 0xfeedface, 1700000000]
+    words += blkptr()
+    words += [5000, MMP_MAGIC, 1000000000, mmp_config, 0]
+    return struct.pack(e + '%dQ' % len(words), *words)
+
+
+# The checksum words are stored in the byte order of the block, but computed
+# over the digest read as big endian words.
+def seal(block, offset, e):
+    block[-40:] = struct.pack(e + '5Q', ZEC_MAGIC, offset, 0, 0, 0)
+    digest = hashlib.sha256(bytes(block)).digest()
+    block[-32:] = struct.pack(e + '4Q', *struct.unpack('>4Q', digest))
+
+
+if __name__ == '__main__':
+    for e, suffix in (('>', 'be'), ('<', 'le')):
+        slot = bytearray(SLOT_SIZE)
+        ub = uberblock(e)
+        slot[:len(ub)] = ub
+        seal(slot, SLOT_OFFSET, e)
+        with open(f'uberblock_{suffix}.bin', 'wb') as f:
+            f.write(slot)
@@ -0,0 +1,132 @@
+//! Block pointers (`blkptr_t`): where a block is stored, up to three times,
+//! and how to read it back.
+
+use binary::{Endianness, Result, SliceReader};
+
+/// Size of a block pointer on disk.
+pub const BLKPTR_SIZE: usize = 128;
+
+/// Sizes in block pointers are in 512 byte sectors.
+const SPA_MINBLOCKSHIFT: u64 = 9;
+
+/// Extracts `len` bits of `x` starting from bit `low`.
+fn bits(x: u64, low: u32, len: u32) -> u64 {
+    (x >> low) & ((1 << len) - 1)
+}
+
+/// A data virtual address (`dva_t`): a location on a top-level vdev.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dva(pub [u64; 2]);
+
+impl Dva {
+    pub fn vdev(&self) -> u64 {
+        bits(self.0[0], 32, 24)
+    }
+
+    /// Allocated size in bytes, including the RAID-Z parity and gang headers.
+    pub fn asize(&self) -> u64 {
+        bits(self.0[0], 0, 24) << SPA_MINBLOCKSHIFT
+    }
+
+    /// Offset in bytes, from the end of the front labels of the vdev.
+    pub fn offset(&self) -> u64 {
+        bits(self.0[1], 0, 63) << SPA_MINBLOCKSHIFT
+    }
+
+    pub fn is_gang(&self) -> bool {
+        bits(self.0[1], 63, 1) == 1
+    }
+
+    /// Unused DVAs are all zeros.
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0, 0]
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Blkptr {
+    pub dva: [Dva; 3],
+    /// Sizes, compression, checksum, type, level and flags (`blk_prop`).
+    pub prop: u64,
+    pub pad: [u64; 2],
+    pub phys_birth: u64,
+    /// The txg the block was written in.
+    pub birth: u64,
+    /// Number of non-zero blocks under this one.
+    pub fill: u64,
+    pub checksum: [u64; 4],
+}
+
+impl Blkptr {
+    /// Reads a block pointer written in the given byte order: the one of the
+    /// structure that holds it.
+    pub fn read(r: &mut SliceReader, e: Endianness) -> Result<Self> {
+        let mut w = [0u64; BLKPTR_SIZE / 8];
+        for word in w.iter_mut() {
+            *word = r.read_int(e)?;
+        }
+        Ok(Blkptr {
+            dva: [Dva([w[0], w[1]]), Dva([w[2], w[3]]), Dva([w[4], w[5]])],
+            prop: w[6],
+            pad: [w[7], w[8]],
+            phys_birth: w[9],
+            birth: w[10],
+            fill: w[11],
+            checksum: [w[12], w[13], w[14], w[15]],
+        })
+    }
+
+    /// Logical size in bytes. Meaningless for embedded block pointers.
+    pub fn lsize(&self) -> u64 {
+        (bits(self.prop, 0, 16) + 1) << SPA_MINBLOCKSHIFT
+    }
+
+    /// Physical size in bytes. Meaningless for embedded block pointers.
+    pub fn psize(&self) -> u64 {
+        (bits(self.prop, 16, 16) + 1) << SPA_MINBLOCKSHIFT
+    }
+
+    pub fn compression(&self) -> u64 {
+        bits(self.prop, 32, 7)
+    }
+
+    /// The data is stored in the block pointer itself.
+    pub fn is_embedded(&self) -> bool {
+        bits(self.prop, 39, 1) == 1
+    }
+
+    pub fn checksum_type(&self) -> u64 {
+        bits(self.prop, 40, 8)
+    }
+
+    pub fn object_type(&self) -> u64 {
+        bits(self.prop, 48, 8)
+    }
+
+    /// Indirection level: 0 for data blocks.
+    pub fn level(&self) -> u64 {
+        bits(self.prop, 56, 5)
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        bits(self.prop, 61, 1) == 1
+    }
+
+    pub fn is_dedup(&self) -> bool {
+        bits(self.prop, 62, 1) == 1
+    }
+
+    /// Byte order of the block it points to, the one of the host that
+    /// wrote it.
+    pub fn byte_order(&self) -> Endianness {
+        match bits(self.prop, 63, 1) {
+            1 => Endianness::Little,
+            _ => Endianness::Big,
+        }
+    }
+
+    /// A hole is a block pointer that points nowhere.
+    pub fn is_hole(&self) -> bool {
+        !self.is_embedded() && self.dva[0].is_empty()
+    }
+}
@@ -0,0 +1,124 @@
+//! Embedded checksums (`zio_eck_t`), which protect the blocks that can't be
+//! pointed to by a block pointer: the label nvlist and the uberblocks.
+//!
+//! The last 40 bytes of such a block hold a magic number followed by the
+//! SHA-256 of the block, computed with those 32 bytes set to the verifier: the
+//! offset of the block on the vdev followed by three zeros. Both the magic
+//! and the checksum words are in the byte order of the host that wrote the
+//! block, while the digest itself is read as big endian words.
+
+use std::fmt::{Display, Formatter};
+
+use binary::Endianness;
+use sha2::{Digest, Sha256};
+
+pub const ZEC_MAGIC: u64 = 0x0210da7ab10c7a11;
+
+const ECK_SIZE: usize = 8 + 32;
+
+/// Verifies the embedded checksum of a block read at the given offset of the
+/// vdev, returning the byte order it was written in.
+pub fn verify(block: &[u8], offset: u64) -> Result<Endianness, ChecksumError> {
+    let eck_offset = block.len().checked_sub(ECK_SIZE)
+        .ok_or(ChecksumError::TooSmall(block.len()))?;
+    let (data, eck) = block.split_at(eck_offset);
+    let (magic, expected) = eck.split_at(8);
+
+    let magic: [u8; 8] = magic.try_into().unwrap();
+    let endianness = Endianness::detect(magic, ZEC_MAGIC)
+        .ok_or(ChecksumError::Magic(u64::from_be_bytes(magic)))?;
+    let expected = words(expected, |x| endianness.u64(x));
+
+    let verifier = [offset, 0, 0, 0].map(|x| endianness.encode(x));
+    let mut sha = Sha256::new();
+    sha.update(data);
+    sha.update(magic);
+    verifier.iter().for_each(|x| sha.update(x));
+    let actual = words(&sha.finalize(), u64::from_be_bytes);
+
+    if actual == expected {
+        Ok(endianness)
+    } else {
+        Err(ChecksumError::Mismatch { expected, actual })
+    }
+}
+
+fn words(x: &[u8], f: impl Fn([u8; 8]) -> u64) -> [u64; 4] {
+    let mut words = [0; 4];
+    for (word, bytes) in words.iter_mut().zip(x.chunks_exact(8)) {
+        *word = f(bytes.try_into().unwrap());
+    }
+    words
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChecksumError {
+    /// The block can't hold a `zio_eck_t`.
+    TooSmall(usize),
+    /// The magic number, read as big endian, matches neither byte order: the
+    /// block doesn't hold a checksum at all.
+    Magic(u64),
+    Mismatch { expected: [u64; 4], actual: [u64; 4] },
+}
+
+impl Display for ChecksumError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChecksumError::TooSmall(n) =>
+                write!(f, "block of {} bytes is too small to be checksummed", n),
+            ChecksumError::Magic(x) =>
+                write!(f, "bad embedded checksum magic {:#018x}", x),
+            ChecksumError::Mismatch { expected, actual } => write!(
+                f, "checksum mismatch: expected {}, got {}", hex(expected), hex(actual)
+            ),
+        }
+    }
+}
+
+fn hex(x: &[u64; 4]) -> String {
+    x.iter().map(|w| format!("{:016x}", w)).collect::<Vec<_>>().join(":")
+}
+
+impl std::error::Error for ChecksumError {}
+
+impl From<ChecksumError> for binary::Error {
+    fn from(e: ChecksumError) -> Self {
+        binary::Error::invalid(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Uberblock ring slots sealed at OFFSET, packed by fixtures/pack.py.
+    const BE: &[u8] = include_bytes!("../fixtures/uberblock_be.bin");
+    const LE: &[u8] = include_bytes!("../fixtures/uberblock_le.bin");
+    const OFFSET: u64 = 128 * 1024;
+
+    #[test]
+    fn verify_both_byte_orders() {
+        assert_eq!(verify(BE, OFFSET), Ok(Endianness::Big));
+        assert_eq!(verify(LE, OFFSET), Ok(Endianness::Little));
+    }
+
+    // The offset is part of the checksum, so a block read from elsewhere
+    // doesn't verify.
+    #[test]
+    fn wrong_offset() {
+        assert!(matches!(verify(LE, OFFSET + 4096), Err(ChecksumError::Mismatch { .. })));
+    }
+
+    #[test]
+    fn corrupted() {
+        let mut block = BE.to_vec();
+        block[100] ^= 1;
+        assert!(matches!(verify(&block, OFFSET), Err(ChecksumError::Mismatch { .. })));
+    }
+
+    #[test]
+    fn no_checksum() {
+        assert_eq!(verify(&[0; 4096], OFFSET), Err(ChecksumError::Magic(0)));
+        assert_eq!(verify(&[0; 39], OFFSET), Err(ChecksumError::TooSmall(39)));
+    }
+}
@@ -1,2 +1,5 @@
+pub mod blkptr;
+pub mod checksum;
+pub mod uberblock;
 pub mod unix;
 pub mod vdev;
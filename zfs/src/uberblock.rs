@@ -0,0 +1,179 @@
+//! The uberblock (`uberblock_t`), the root of the block tree of a pool. Every
+//! label holds a ring of them, written in turn at each txg.
+
+use std::cmp::Ordering;
+
+use binary::{Context, Endianness, Error, Result, SliceReader};
+use enum_macros::bitflags;
+
+use crate::blkptr::{Blkptr, BLKPTR_SIZE};
+
+pub const UBERBLOCK_MAGIC: u64 = 0x00bab10c;
+pub const MMP_MAGIC: u64 = 0xa11cea11;
+
+/// Size of the fields we know of, up to `ub_checkpoint_txg`.
+const UBERBLOCK_SIZE: usize = 5 * 8 + BLKPTR_SIZE + 5 * 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Uberblock {
+    /// Byte order of the host that wrote the uberblock, given by its magic.
+    pub endianness: Endianness,
+    /// SPA version (`ub_version`).
+    pub version: u64,
+    /// The txg the uberblock was written in.
+    pub txg: u64,
+    /// Sum of the GUIDs of all the vdevs of the pool.
+    pub guid_sum: u64,
+    /// Seconds since the epoch.
+    pub timestamp: u64,
+    /// The block pointer of the meta object set.
+    pub rootbp: Blkptr,
+    /// SPA version of the software that wrote the uberblock.
+    pub software_version: u64,
+    /// `MMP_MAGIC` if the MMP fields are valid.
+    pub mmp_magic: u64,
+    /// Nanoseconds between MMP writes.
+    pub mmp_delay: u64,
+    /// MMP interval, sequence number, fail intervals and which are valid.
+    pub mmp_config: u64,
+    /// The txg of the pool checkpoint, or 0.
+    pub checkpoint_txg: u64,
+}
+
+/// Which fields of `ub_mmp_config` are valid.
+#[bitflags(u8)]
+pub enum MmpValid {
+    Interval = 0x01,
+    Seq = 0x02,
+    FailInt = 0x04,
+}
+
+impl Uberblock {
+    /// Decodes an uberblock from the start of a ring slot, in the byte order
+    /// given by its magic.
+    pub fn decode(slot: &[u8]) -> Result<Self> {
+        Self::decode_words(slot).decoding("zfs::Uberblock")
+    }
+
+    fn decode_words(slot: &[u8]) -> Result<Self> {
+        if slot.len() < UBERBLOCK_SIZE {
+            return Err(Error::invalid(format!(
+                "slot of {} bytes is too small to hold an uberblock", slot.len()
+            )))
+        }
+        let mut r = SliceReader::new(slot);
+        let magic = *r.read_array::<8>()?;
+        let e = Endianness::detect(magic, UBERBLOCK_MAGIC).ok_or_else(|| Error::invalid(
+            format!("bad uberblock magic {:#018x}", u64::from_be_bytes(magic))
+        ))?;
+        Ok(Uberblock {
+            endianness: e,
+            version: r.read_int(e)?,
+            txg: r.read_int(e)?,
+            guid_sum: r.read_int(e)?,
+            timestamp: r.read_int(e)?,
+            rootbp: Blkptr::read(&mut r, e).field("rootbp")?,
+            software_version: r.read_int(e)?,
+            mmp_magic: r.read_int(e)?,
+            mmp_delay: r.read_int(e)?,
+            mmp_config: r.read_int(e)?,
+            checkpoint_txg: r.read_int(e)?,
+        })
+    }
+
+    /// Whether the uberblock was written by a host with MMP support.
+    pub fn mmp_valid(&self) -> bool {
+        self.mmp_magic == MMP_MAGIC
+    }
+
+    pub fn mmp_flags(&self) -> MmpValid {
+        match self.mmp_valid() {
+            true => MmpValid::from_bits_retain(self.mmp_config as u8),
+            false => MmpValid::empty(),
+        }
+    }
+
+    /// Milliseconds between MMP writes.
+    pub fn mmp_interval(&self) -> Option<u64> {
+        self.mmp_field(MmpValid::INTERVAL, 8, 24)
+    }
+
+    /// Sequence number of the MMP writes in the same txg.
+    pub fn mmp_seq(&self) -> Option<u64> {
+        self.mmp_field(MmpValid::SEQ, 32, 16)
+    }
+
+    /// Number of missed MMP intervals after which the pool is suspended.
+    pub fn mmp_fail_intervals(&self) -> Option<u64> {
+        self.mmp_field(MmpValid::FAIL_INT, 48, 16)
+    }
+
+    fn mmp_field(&self, flag: MmpValid, low: u32, len: u32) -> Option<u64> {
+        self.mmp_flags().contains(flag)
+            .then(|| (self.mmp_config >> low) & ((1 << len) - 1))
+    }
+
+    /// Orders uberblocks as `vdev_uberblock_compare` does: by txg, then by
+    /// timestamp, then by MMP sequence number. The greatest is the active one.
+    pub fn compare(&self, other: &Uberblock) -> Ordering {
+        self.txg.cmp(&other.txg)
+            .then(self.timestamp.cmp(&other.timestamp))
+            .then(self.mmp_seq().unwrap_or(0).cmp(&other.mmp_seq().unwrap_or(0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Packed by fixtures/pack.py.
+    const BE: &[u8] = include_bytes!("../fixtures/uberblock_be.bin");
+    const LE: &[u8] = include_bytes!("../fixtures/uberblock_le.bin");
+
+    #[test]
+    fn decode() -> Result<()> {
+        for (slot, endianness) in [(BE, Endianness::Big), (LE, Endianness::Little)] {
+            let ub = Uberblock::decode(slot)?;
+            assert_eq!(ub.endianness, endianness);
+            assert_eq!(ub.version, 5000);
+            assert_eq!(ub.txg, 1234);
+            assert_eq!(ub.guid_sum, 0xfeedface);
+            assert_eq!(ub.timestamp, 1700000000);
+            assert_eq!(ub.software_version, 5000);
+            assert_eq!(ub.checkpoint_txg, 0);
+
+            assert_eq!(ub.rootbp.dva[0].vdev(), 1);
+            assert_eq!(ub.rootbp.dva[0].offset(), 0x400 << 9);
+            assert_eq!(ub.rootbp.birth, 1234);
+            assert_eq!(ub.rootbp.fill, 77);
+            assert_eq!(ub.rootbp.checksum, [1, 2, 3, 4]);
+            assert_eq!(ub.rootbp.byte_order(), Endianness::Little);
+            assert_eq!(ub.rootbp.object_type(), 11);
+            assert_eq!(ub.rootbp.lsize(), 1024);
+
+            assert!(ub.mmp_valid());
+            assert_eq!(ub.mmp_flags(), MmpValid::all());
+            assert_eq!(ub.mmp_interval(), Some(1000));
+            assert_eq!(ub.mmp_seq(), Some(3));
+            assert_eq!(ub.mmp_fail_intervals(), Some(10));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn decode_garbage() {
+        assert!(Uberblock::decode(&[0; 4096]).is_err());
+        assert!(Uberblock::decode(&BE[..64]).is_err());
+    }
+
+    #[test]
+    fn compare() -> Result<()> {
+        let ub = Uberblock::decode(LE)?;
+        let newer = Uberblock { txg: ub.txg + 1, timestamp: 0, ..ub.clone() };
+        let later = Uberblock { timestamp: ub.timestamp + 1, ..ub.clone() };
+        assert_eq!(newer.compare(&ub), Ordering::Greater);
+        assert_eq!(later.compare(&ub), Ordering::Greater);
+        assert_eq!(ub.compare(&Uberblock::decode(BE)?), Ordering::Equal);
+        Ok(())
+    }
+}
@@ -37,8 +37,12 @@ impl BlockDevice {
 
     pub fn size(&self) -> std::io::Result<u64> {
         let path = self.path()?.with_file_name("size");
-        let blocks = std::fs::read_to_string(path)?.trim().parse::<u64>().unwrap();
-        Ok(blocks * UNIX_BLOCK_SIZE)
+        let blocks = std::fs::read_to_string(path)?.trim().parse::<u64>()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        blocks.checked_mul(UNIX_BLOCK_SIZE).ok_or_else(|| std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("device size of {} blocks overflows", blocks)
+        ))
     }
 
     fn path(&self) -> std::io::Result<PathBuf> {
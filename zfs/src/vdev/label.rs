@@ -1,33 +1,488 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::io::SeekFrom;
 
-use binary::Reader;
-use nvlist::Nvlist;
+use binary::{Context, Error, Reader, Result};
+use nvlist::{LookupError, Nvlist};
 
+use crate::checksum::{self, ChecksumError};
+use crate::uberblock::Uberblock;
 use crate::vdev::physical::Physical;
 
 const LABEL_SIZE: u64 = 256 * 1024;
 const LABEL_NVLIST_OFFSET: u64 = 16 * 1024;
+const LABEL_NVLIST_SIZE: usize = 112 * 1024;
+const UBERBLOCK_RING_OFFSET: u64 = 128 * 1024;
+const UBERBLOCK_RING_SIZE: usize = 128 * 1024;
+const UBERBLOCK_SHIFT: u64 = 10;
+const MAX_UBERBLOCK_SHIFT: u64 = 13;
 
-#[derive(Debug)]
+/// Fields that all the labels of a vdev must agree on, by their names in the
+/// label nvlist.
+const AGREED_FIELDS: [&str; 4] = ["txg", "pool_guid", "guid", "state"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum LabelNumber { L0, L1, L2, L3, }
 
-fn offset(physical_vdev_size: u64, label: LabelNumber) -> u64 {
+impl LabelNumber {
+    /// L0 and L1 are at the front of the vdev, L2 and L3 at the back.
+    pub const ALL: [LabelNumber; 4] = [
+        LabelNumber::L0, LabelNumber::L1, LabelNumber::L2, LabelNumber::L3
+    ];
+}
+
+impl Display for LabelNumber {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let n = match self {
+            LabelNumber::L0 => 0,
+            LabelNumber::L1 => 1,
+            LabelNumber::L2 => 2,
+            LabelNumber::L3 => 3,
+        };
+        write!(f, "{}", n)
+    }
+}
+
+fn offset(physical_vdev_size: u64, label: LabelNumber) -> Result<u64> {
+    // Like vdev_open, only whole labels count, so that the back labels are
+    // found on vdevs whose size isn't a multiple of the label size.
+    let physical_vdev_size = physical_vdev_size & !(LABEL_SIZE - 1);
     let base_offset = match label {
         LabelNumber::L0 | LabelNumber::L1 => 0,
-        LabelNumber::L2 | LabelNumber::L3 => physical_vdev_size - LABEL_SIZE * 2,
+        LabelNumber::L2 | LabelNumber::L3 => physical_vdev_size
+            .checked_sub(LABEL_SIZE * 2)
+            .ok_or_else(|| Error::invalid(format!(
+                "vdev of {} bytes is too small to hold labels", physical_vdev_size
+            )))?,
     };
     let offset = match label {
         LabelNumber::L0 | LabelNumber::L2 => 0,
         LabelNumber::L1 | LabelNumber::L3 => 1,
     };
-    base_offset + offset * LABEL_SIZE
+    Ok(base_offset + offset * LABEL_SIZE)
+}
+
+/// The nvlist of a label and, if it was read despite its checksum, why the
+/// checksum doesn't match.
+#[derive(Debug)]
+pub struct Label {
+    nvlist: Nvlist,
+    checksum_error: Option<ChecksumError>,
+}
+
+impl Label {
+    pub fn nvlist(&self) -> &Nvlist {
+        &self.nvlist
+    }
+
+    pub fn checksum_error(&self) -> Option<&ChecksumError> {
+        self.checksum_error.as_ref()
+    }
+
+    /// The ashift of the top-level vdev, which sets the size of the uberblock
+    /// slots.
+    pub fn ashift(&self) -> std::result::Result<u64, LookupError> {
+        self.nvlist.get_nvlist("vdev_tree")?.get_u64("ashift")
+    }
+}
+
+/// Reads a label, failing if its checksum doesn't match unless `force` is
+/// set, in which case the nvlist is decoded anyway.
+pub fn read_label(
+    vdev: &Physical,
+    number: LabelNumber,
+    force: bool
+) -> Result<Label> {
+    let offset = offset(vdev.size()?, number)? + LABEL_NVLIST_OFFSET;
+    let mut r = Reader::new(vdev.file());
+    r.seek(SeekFrom::Start(offset))?;
+    let mut block = vec![0; LABEL_NVLIST_SIZE];
+    r.read(&mut block)?;
+    let checksum_error = match checksum::verify(&block, offset) {
+        Ok(_) => None,
+        Err(e) if force => Some(e),
+        Err(e) => return Err(e).at(offset),
+    };
+    // The nvlist can't extend past the block, which the checksum covers.
+    let nvlist = Nvlist::decode(&block).within(offset)?;
+    Ok(Label { nvlist, checksum_error })
+}
+
+/// Reads the nvlist of a label, failing if its checksum doesn't match.
+pub fn read_nvlist(vdev: &Physical, number: LabelNumber) -> Result<Nvlist> {
+    read_label(vdev, number, false).map(|l| l.nvlist)
+}
+
+/// The four labels of a vdev, or why they couldn't be read.
+#[derive(Debug)]
+pub struct Labels([Result<Label>; 4]);
+
+impl Labels {
+    /// Reads the labels as read_label does.
+    pub fn read(vdev: &Physical, force: bool) -> Self {
+        Labels(LabelNumber::ALL.map(|n| read_label(vdev, n, force)))
+    }
+
+    pub fn get(&self, number: LabelNumber) -> &Result<Label> {
+        &self.0[number as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LabelNumber, &Result<Label>)> {
+        LabelNumber::ALL.into_iter().zip(self.0.iter())
+    }
+
+    /// The label to trust, picked as `vdev_label_read_config` does on import:
+    /// the readable label with the highest txg or, if a label without a txg
+    /// comes first, that one. Spares, caches and vdevs that haven't been
+    /// fully initialized have no txg. Labels whose checksum doesn't match are
+    /// only considered if no other label could be read.
+    pub fn authoritative(&self) -> Option<(LabelNumber, &Label)> {
+        self.pick(|l| l.checksum_error.is_none()).or_else(|| self.pick(|_| true))
+    }
+
+    fn pick(&self, f: impl Fn(&Label) -> bool) -> Option<(LabelNumber, &Label)> {
+        let mut best: Option<(LabelNumber, &Label, u64)> = None;
+        for (number, label) in self.iter() {
+            let Ok(label) = label else { continue };
+            if !f(label) {
+                continue
+            }
+            match label.nvlist.get_u64("txg") {
+                Ok(txg) if txg > 0 => match best {
+                    Some((_, _, best_txg)) if best_txg >= txg => {}
+                    _ => best = Some((number, label, txg)),
+                },
+                _ if best.is_none() => return Some((number, label)),
+                _ => {}
+            }
+        }
+        best.map(|(number, label, _)| (number, label))
+    }
+
+    /// The fields of the readable labels that differ from the authoritative
+    /// label, e.g. because the back of the vdev wasn't written by the last
+    /// transaction or was overwritten.
+    pub fn mismatches(&self) -> Vec<Mismatch> {
+        let Some((authoritative, expected)) = self.authoritative() else {
+            return Vec::new()
+        };
+        let mut mismatches = Vec::new();
+        for (label, other) in self.iter().filter(|(n, _)| *n != authoritative) {
+            let Ok(other) = other else { continue };
+            for field in AGREED_FIELDS {
+                let value = other.nvlist.get_u64(field).ok();
+                let expected = expected.nvlist.get_u64(field).ok();
+                if value != expected {
+                    mismatches.push(Mismatch {
+                        label, field, value, authoritative, expected
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+}
+
+/// A field whose value in a label differs from the authoritative label.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub label: LabelNumber,
+    pub field: &'static str,
+    pub value: Option<u64>,
+    pub authoritative: LabelNumber,
+    pub expected: Option<u64>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = |x: Option<u64>| x.map_or("none".to_string(), |x| x.to_string());
+        write!(
+            f, "label {} has {} {}, label {} has {}",
+            self.label, self.field, value(self.value),
+            self.authoritative, value(self.expected)
+        )
+    }
+}
+
+/// Size of the uberblock slots of a vdev: the larger of its sector size and
+/// 1 KiB, but no more than 8 KiB, so that the ring holds at least 16 of them.
+pub fn uberblock_slot_size(ashift: u64) -> usize {
+    1 << ashift.clamp(UBERBLOCK_SHIFT, MAX_UBERBLOCK_SHIFT)
 }
 
-pub fn read_nvlist(
+/// Reads the uberblock ring of a label. Each slot holds an uberblock or why
+/// it doesn't: a checksum mismatch or a bad magic, as in never written.
+pub fn read_uberblocks(
     vdev: &Physical,
-    number: LabelNumber
-) -> std::io::Result<Nvlist> {
+    number: LabelNumber,
+    ashift: u64
+) -> Result<Vec<Result<Uberblock>>> {
+    let offset = offset(vdev.size()?, number)? + UBERBLOCK_RING_OFFSET;
     let mut r = Reader::new(vdev.file());
-    r.seek(SeekFrom::Start(offset(vdev.size()?, number) + LABEL_NVLIST_OFFSET))?;
-    Nvlist::read(&mut r)
+    r.seek(SeekFrom::Start(offset))?;
+    let mut ring = vec![0; UBERBLOCK_RING_SIZE];
+    r.read(&mut ring)?;
+
+    let slot_size = uberblock_slot_size(ashift);
+    Ok(ring.chunks_exact(slot_size).enumerate().map(|(i, slot)| {
+        let offset = offset + (i * slot_size) as u64;
+        checksum::verify(slot, offset).at(offset)?;
+        Uberblock::decode(slot).at(offset)
+    }).collect())
+}
+
+/// An uberblock and where it was found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RingEntry {
+    pub label: LabelNumber,
+    pub slot: usize,
+    pub uberblock: Uberblock,
+}
+
+/// The uberblock rings of the four labels of a vdev, or why they couldn't be
+/// read.
+#[derive(Debug)]
+pub struct Rings([Result<Vec<Result<Uberblock>>>; 4]);
+
+impl Rings {
+    /// Reads the rings as read_uberblocks does.
+    pub fn read(vdev: &Physical, ashift: u64) -> Self {
+        Rings(LabelNumber::ALL.map(|n| read_uberblocks(vdev, n, ashift)))
+    }
+
+    pub fn get(&self, number: LabelNumber) -> &Result<Vec<Result<Uberblock>>> {
+        &self.0[number as usize]
+    }
+
+    pub fn iter(
+        &self
+    ) -> impl Iterator<Item = (LabelNumber, &Result<Vec<Result<Uberblock>>>)> {
+        LabelNumber::ALL.into_iter().zip(self.0.iter())
+    }
+
+    /// The valid uberblocks of the rings that could be read.
+    pub fn uberblocks(&self) -> impl Iterator<Item = RingEntry> + '_ {
+        self.iter()
+            .filter_map(|(label, ring)| Some((label, ring.as_ref().ok()?)))
+            .flat_map(|(label, ring)| ring.iter().enumerate().filter_map(
+                move |(slot, uberblock)| Some(RingEntry {
+                    label,
+                    slot,
+                    uberblock: uberblock.as_ref().ok()?.clone(),
+                })
+            ))
+    }
+
+    /// The valid uberblocks, newest first by Uberblock::compare: the
+    /// candidates to open the pool from when rewinding like `zpool import -F`.
+    pub fn candidates(&self) -> Vec<RingEntry> {
+        let mut entries: Vec<RingEntry> = self.uberblocks().collect();
+        entries.sort_by(|a, b| b.uberblock.compare(&a.uberblock));
+        entries
+    }
+
+    /// The active uberblock: the greatest by Uberblock::compare across all
+    /// labels or, on ties, the first found, as vdev_uberblock_load picks it.
+    pub fn active(&self) -> Option<RingEntry> {
+        self.rewind(u64::MAX)
+    }
+
+    /// The uberblock that would be active if the pool had stopped at the
+    /// given txg: the active one among those written up to it, like
+    /// `zpool import -T txg`.
+    pub fn rewind(&self, txg: u64) -> Option<RingEntry> {
+        self.uberblocks()
+            .filter(|e| e.uberblock.txg <= txg)
+            .reduce(|best, e| match e.uberblock.compare(&best.uberblock) {
+                Ordering::Greater => e,
+                _ => best,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nvlist::{Builder, Encoding, Endianness};
+
+    use super::*;
+    use crate::blkptr::Blkptr;
+
+    fn label(txg: Option<u64>, pool_guid: u64) -> Result<Label> {
+        let mut builder = Builder::new().add("pool_guid", pool_guid).add("guid", 1u64);
+        if let Some(txg) = txg {
+            builder = builder.add("txg", txg);
+        }
+        let nvlist = Nvlist::new(builder.build(), Encoding::Xdr, Endianness::Big);
+        Ok(Label { nvlist, checksum_error: None })
+    }
+
+    fn txg(txg: u64) -> Result<Label> {
+        label(Some(txg), 1)
+    }
+
+    // Read despite its checksum, as done with force set.
+    fn corrupt(txg: u64) -> Result<Label> {
+        let mut label = label(Some(txg), 1)?;
+        label.checksum_error = Some(ChecksumError::Magic(0));
+        Ok(label)
+    }
+
+    fn unreadable() -> Result<Label> {
+        Err(Error::invalid("unreadable label"))
+    }
+
+    fn authoritative(labels: &Labels) -> Option<LabelNumber> {
+        labels.authoritative().map(|(number, _)| number)
+    }
+
+    #[test]
+    fn offsets() -> Result<()> {
+        let size = 10 * LABEL_SIZE;
+        let offsets = LabelNumber::ALL.map(|n| offset(size, n).unwrap());
+        assert_eq!(offsets, [0, LABEL_SIZE, 8 * LABEL_SIZE, 9 * LABEL_SIZE]);
+        // The back labels end at the last whole label, not at the end of the
+        // vdev.
+        for extra in [1, 4096, LABEL_SIZE - 1] {
+            assert_eq!(LabelNumber::ALL.map(|n| offset(size + extra, n).unwrap()), offsets);
+        }
+        assert_eq!(offset(LABEL_SIZE, LabelNumber::L0)?, 0);
+        assert!(offset(2 * LABEL_SIZE - 1, LabelNumber::L2).is_err());
+        Ok(())
+    }
+
+    // Ties go to the first label, as vdev_label_read_config only replaces the
+    // label it keeps with one of a higher txg.
+    #[test]
+    fn highest_txg_wins() {
+        let labels = Labels([txg(5), txg(7), txg(7), txg(6)]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L1));
+        let labels = Labels([unreadable(), unreadable(), unreadable(), txg(2)]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L3));
+        let labels = Labels([unreadable(), unreadable(), unreadable(), unreadable()]);
+        assert_eq!(authoritative(&labels), None);
+        assert!(labels.mismatches().is_empty());
+    }
+
+    #[test]
+    fn without_txg() {
+        let labels = Labels([unreadable(), label(None, 1), txg(9), txg(9)]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L1));
+        // Once a label with a txg is found, those without one are skipped.
+        let labels = Labels([txg(3), label(None, 1), txg(4), unreadable()]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L2));
+        // A txg of 0 is the same as none.
+        let labels = Labels([txg(0), txg(4), unreadable(), unreadable()]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L0));
+    }
+
+    #[test]
+    fn mismatches() {
+        let labels = Labels([txg(8), txg(8), label(Some(7), 2), unreadable()]);
+        let mismatches = labels.mismatches();
+        assert_eq!(mismatches, [
+            Mismatch {
+                label: LabelNumber::L2,
+                field: "txg",
+                value: Some(7),
+                authoritative: LabelNumber::L0,
+                expected: Some(8),
+            },
+            Mismatch {
+                label: LabelNumber::L2,
+                field: "pool_guid",
+                value: Some(2),
+                authoritative: LabelNumber::L0,
+                expected: Some(1),
+            },
+        ]);
+        assert_eq!(mismatches[0].to_string(), "label 2 has txg 7, label 0 has 8");
+
+        let labels = Labels([txg(8), label(None, 1), unreadable(), unreadable()]);
+        assert_eq!(labels.mismatches()[0].to_string(), "label 1 has txg none, label 0 has 8");
+    }
+
+    #[test]
+    fn checksum_fallback() {
+        // A higher txg doesn't make up for a bad checksum.
+        let labels = Labels([corrupt(9), txg(8), unreadable(), txg(7)]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L1));
+        let labels = Labels([unreadable(), corrupt(9), corrupt(10), unreadable()]);
+        assert_eq!(authoritative(&labels), Some(LabelNumber::L2));
+        let (_, label) = labels.authoritative().unwrap();
+        assert_eq!(label.checksum_error(), Some(&ChecksumError::Magic(0)));
+    }
+
+    fn uberblock(txg: u64, timestamp: u64) -> Result<Uberblock> {
+        Ok(Uberblock {
+            endianness: Endianness::Little,
+            version: 5000,
+            txg,
+            guid_sum: 0,
+            timestamp,
+            rootbp: Blkptr::default(),
+            software_version: 5000,
+            mmp_magic: 0,
+            mmp_delay: 0,
+            mmp_config: 0,
+            checkpoint_txg: 0,
+        })
+    }
+
+    fn rings() -> Result<Rings> {
+        Ok(Rings([
+            Ok(vec![uberblock(10, 1), Err(Error::invalid("unused")), uberblock(12, 1)]),
+            Err(Error::invalid("can't read the ring")),
+            Ok(vec![uberblock(12, 2), uberblock(11, 5)]),
+            Ok(vec![]),
+        ]))
+    }
+
+    fn found(entry: Option<RingEntry>) -> Option<(LabelNumber, usize)> {
+        entry.map(|e| (e.label, e.slot))
+    }
+
+    #[test]
+    fn unreadable_rings_are_kept() -> Result<()> {
+        let rings = rings()?;
+        assert!(rings.get(LabelNumber::L1).is_err());
+        assert_eq!(rings.iter().filter(|(_, ring)| ring.is_err()).count(), 1);
+        assert_eq!(rings.uberblocks().count(), 4);
+        Ok(())
+    }
+
+    // Among the uberblocks of txg 12, the later timestamp wins, wherever it
+    // is found.
+    #[test]
+    fn active() -> Result<()> {
+        assert_eq!(found(rings()?.active()), Some((LabelNumber::L2, 0)));
+        let rings = Rings([Ok(vec![]), Err(Error::invalid("unused")), Ok(vec![]), Ok(vec![])]);
+        assert_eq!(found(rings.active()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn rewind() -> Result<()> {
+        let rings = rings()?;
+        assert_eq!(found(rings.rewind(u64::MAX)), found(rings.active()));
+        assert_eq!(found(rings.rewind(12)), Some((LabelNumber::L2, 0)));
+        assert_eq!(found(rings.rewind(11)), Some((LabelNumber::L2, 1)));
+        assert_eq!(found(rings.rewind(10)), Some((LabelNumber::L0, 0)));
+        assert_eq!(found(rings.rewind(9)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn candidates() -> Result<()> {
+        let candidates = rings()?.candidates();
+        let found: Vec<_> = candidates.iter()
+            .map(|e| (e.uberblock.txg, e.label, e.slot))
+            .collect();
+        assert_eq!(found, [
+            (12, LabelNumber::L2, 0),
+            (12, LabelNumber::L0, 2),
+            (11, LabelNumber::L2, 1),
+            (10, LabelNumber::L0, 0),
+        ]);
+        Ok(())
+    }
 }